@@ -21,6 +21,7 @@ fn main() {
     println!("cargo:rustc-env=PHP_EXTENSION_BUILD={}", zend_extension_build);
     println!("cargo:rerun-if-env-changed=PHP_EXTENSION_BUILD");
     set_version_features(api_version);
+    set_build_features(zend_extension_build);
 }
 
 fn execute_command(command: &str, error_message: &str) -> String {
@@ -42,3 +43,9 @@ fn set_version_features(api_version: String) {
         println!("cargo:rustc-cfg=feature=\"php73\"");
     }
 }
+
+fn set_build_features(zend_extension_build: String) {
+    if zend_extension_build.trim().split(',').any(|part| part == "TS") {
+        println!("cargo:rustc-cfg=feature=\"zts\"");
+    }
+}
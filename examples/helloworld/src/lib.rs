@@ -5,8 +5,7 @@ use solder::*;
 use solder::zend::*;
 use solder::info::*;
 
-#[no_mangle]
-pub extern fn php_module_info() {
+fn php_module_info() {
     print_table_start();
     print_table_row("A demo PHP extension written in Rust", "enabled");
     print_table_end();
@@ -25,11 +24,8 @@ pub extern fn get_module() -> *mut zend::Module {
 }
 
 
-#[no_mangle]
-pub extern fn hello_world(_data: &ExecuteData, retval: &mut Zval) {
+pub fn hello_world(data: &ExecuteData, retval: &mut Zval) {
     let mut name_zval = Zval::new_as_null();
-    php_parse_parameters!(&mut name_zval);
-    let name = String::try_from(name_zval).ok().unwrap();
-    let hello = format!("Hello {}", name);
-    php_return!(retval, hello);
+    php_parse_parameters!(data, &mut name_zval);
+    php_return_result!(retval, String::try_from(name_zval).map(|name| format!("Hello {}", name)));
 }
@@ -0,0 +1,89 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::Parser;
+use syn::spanned::Spanned;
+use syn::{Error, Item, ItemFn, ItemStruct, LitStr};
+
+pub fn expand(args: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    match syn::parse2(item)? {
+        Item::Fn(function) => expand_function(args, function),
+        Item::Struct(structure) => expand_struct(args, structure),
+        item => Err(Error::new(item.span(), "#[php_class] must be used on a struct or a function returning a Class")),
+    }
+}
+
+/// A function building the class with `ClassBuilder`
+fn expand_function(args: TokenStream, function: ItemFn) -> syn::Result<TokenStream> {
+    if !args.is_empty() {
+        return Err(Error::new(args.span(), "#[php_class] on a function takes no properties, the class is built by the function"));
+    }
+    if !function.sig.inputs.is_empty() || !function.sig.generics.params.is_empty() {
+        return Err(Error::new(function.sig.span(), "#[php_class] functions can't take arguments or be generic"));
+    }
+    let ident = &function.sig.ident;
+    let registration = registration(quote! { #ident });
+    Ok(quote! {
+        #function
+        #registration
+    })
+}
+
+/// A struct used as the state of the objects, described by its `PhpClass` implementation
+fn expand_struct(args: TokenStream, structure: ItemStruct) -> syn::Result<TokenStream> {
+    if !structure.generics.params.is_empty() {
+        return Err(Error::new(structure.generics.span(), "PHP classes can't be generic"));
+    }
+    let mut name = None;
+    let mut cloneable = false;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse::<LitStr>()?.value());
+            Ok(())
+        } else if meta.path.is_ident("cloneable") {
+            cloneable = true;
+            Ok(())
+        } else {
+            Err(meta.error("unsupported php_class property"))
+        }
+    });
+    parser.parse2(args)?;
+
+    let ident = &structure.ident;
+    let name = name.unwrap_or_else(|| ident.to_string());
+    let state = if cloneable {
+        quote! { with_cloneable_state::<#ident>() }
+    } else {
+        quote! { with_state::<#ident>() }
+    };
+    let registration = registration(quote! { class });
+    Ok(quote! {
+        #structure
+
+        const _: () = {
+            fn class() -> ::solder::zend::Class {
+                let class = ::solder::zend::ClassBuilder::new(concat!(#name, "\0").as_ptr() as *const ::std::os::raw::c_char).#state;
+                <#ident as ::solder::zend::PhpClass>::build(class).build()
+            }
+
+            #registration
+        };
+    })
+}
+
+fn registration(class: TokenStream) -> TokenStream {
+    quote! {
+        #[cfg(not(test))]
+        ::solder::inventory::submit! {
+            ::solder::zend::CrateItem {
+                crate_name: env!("CARGO_CRATE_NAME"),
+                module_path: module_path!(),
+                line: line!(),
+                kind: ::solder::zend::CrateItemKind::Class(#class),
+            }
+        }
+
+        // Unit tests are built without PHP to link it, so the item is only marked as used
+        #[cfg(test)]
+        const _: ::solder::zend::CrateItemKind = ::solder::zend::CrateItemKind::Class(#class);
+    }
+}
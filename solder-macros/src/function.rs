@@ -0,0 +1,322 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse::Parser;
+use syn::spanned::Spanned;
+use syn::{Error, FnArg, GenericArgument, Ident, ItemFn, LitStr, Pat, PathArguments, PathSegment, ReturnType, Type};
+
+/// How an argument is read from the call
+enum ArgumentKind {
+    /// The call frame itself, not visible from PHP
+    ExecuteData,
+    /// A type that `parse_parameters` reads directly, with its spec
+    Native(&'static str),
+    /// `ZvalMut`, an argument passed by reference that can be assigned
+    Reference,
+    /// Any other type, read as a Zval and converted with `FromPhpZval`
+    Converted,
+    /// All remaining arguments as `Vec<Zval>` or `Vec<ZvalRef>`
+    Variadic,
+}
+
+struct Argument {
+    name: Ident,
+    ty: Type,
+    kind: ArgumentKind,
+    optional: bool,
+}
+
+pub fn expand(args: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    let function: ItemFn = syn::parse2(item)?;
+    let php_name = parse_php_name(args)?.unwrap_or_else(|| function.sig.ident.to_string().trim_start_matches("r#").to_string());
+    if !function.sig.generics.params.is_empty() {
+        return Err(Error::new(function.sig.generics.span(), "PHP functions can't be generic"));
+    }
+    if let Some(asyncness) = function.sig.asyncness {
+        return Err(Error::new(asyncness.span(), "PHP functions can't be async"));
+    }
+    let arguments = function.sig.inputs.iter().map(parse_argument).collect::<syn::Result<Vec<_>>>()?;
+    let spec = build_spec(&arguments)?;
+
+    let ident = &function.sig.ident;
+    let visibility = &function.vis;
+    let builder = format_ident!("{}_builder", ident);
+    let names = arguments.iter().map(|argument| &argument.name);
+    let call = quote! { #ident(#(#names),*) };
+    let parse = parse_arguments(&arguments, &spec);
+    let set_return = set_return(&function.sig.output, call);
+    let arg_infos = arg_infos(&arguments);
+
+    Ok(quote! {
+        #function
+
+        /// Builder of the PHP function generated by `#[php_function]`
+        #visibility fn #builder() -> ::solder::zend::FunctionBuilder {
+            fn handler(data: &::solder::zend::ExecuteData, retval: &mut ::solder::zend::Zval) {
+                #parse
+                #set_return
+            }
+            ::solder::zend::FunctionBuilder::new(concat!(#php_name, "\0").as_ptr() as *const ::std::os::raw::c_char, handler)
+                #arg_infos
+        }
+
+        #[cfg(not(test))]
+        ::solder::inventory::submit! {
+            ::solder::zend::CrateItem {
+                crate_name: env!("CARGO_CRATE_NAME"),
+                module_path: module_path!(),
+                line: line!(),
+                kind: ::solder::zend::CrateItemKind::Function(#builder),
+            }
+        }
+
+        // Unit tests are built without PHP to link it, so the item is only marked as used
+        #[cfg(test)]
+        const _: ::solder::zend::CrateItemKind = ::solder::zend::CrateItemKind::Function(#builder);
+    })
+}
+
+fn parse_php_name(args: TokenStream) -> syn::Result<Option<String>> {
+    let mut name = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse::<LitStr>()?.value());
+            Ok(())
+        } else {
+            Err(meta.error("unsupported php_function property"))
+        }
+    });
+    parser.parse2(args)?;
+    Ok(name)
+}
+
+fn parse_argument(argument: &FnArg) -> syn::Result<Argument> {
+    let argument = match argument {
+        FnArg::Typed(argument) => argument,
+        FnArg::Receiver(receiver) => return Err(Error::new(receiver.span(), "PHP functions can't take self")),
+    };
+    let name = match &*argument.pat {
+        Pat::Ident(pattern) => pattern.ident.clone(),
+        pattern => return Err(Error::new(pattern.span(), "PHP function arguments must be simple identifiers")),
+    };
+    let ty = (*argument.ty).clone();
+    if is_reference_to(&ty, "ExecuteData") {
+        return Ok(Argument { name, ty, kind: ArgumentKind::ExecuteData, optional: false });
+    }
+    if let Some(inner) = known_type(&ty, "Option").and_then(generic_argument) {
+        return Ok(Argument { name, kind: argument_kind(inner)?, ty, optional: true });
+    }
+    if known_type(&ty, "Vec").and_then(generic_argument).is_some_and(is_zval) {
+        return Ok(Argument { name, ty, kind: ArgumentKind::Variadic, optional: false });
+    }
+    Ok(Argument { name, kind: argument_kind(&ty)?, ty, optional: false })
+}
+
+fn argument_kind(ty: &Type) -> syn::Result<ArgumentKind> {
+    if let Type::Reference(reference) = ty {
+        return match &*reference.elem {
+            Type::Slice(slice) if known_type(&slice.elem, "u8").is_some() => Ok(ArgumentKind::Native("s")),
+            elem if known_type(elem, "str").is_some() => Ok(ArgumentKind::Native("s")),
+            _ => Err(Error::new(ty.span(), "PHP function arguments can only borrow &str, &[u8] and &ExecuteData")),
+        };
+    }
+    let is = |name| known_type(ty, name).is_some();
+    let spec = if is("i64") {
+        "l"
+    } else if is("f64") {
+        "d"
+    } else if is("bool") {
+        "b"
+    } else if is("String") || is("Cow") {
+        "s"
+    } else if is_zval(ty) {
+        "z"
+    } else if is("ZvalMut") {
+        return Ok(ArgumentKind::Reference);
+    } else {
+        return Ok(ArgumentKind::Converted);
+    };
+    Ok(ArgumentKind::Native(spec))
+}
+
+/// Builds the `parse_parameters` spec, checking that optional arguments come last
+fn build_spec(arguments: &[Argument]) -> syn::Result<String> {
+    let mut spec = String::new();
+    let mut has_optional = false;
+    let mut has_variadic = false;
+    for argument in arguments {
+        if has_variadic && !matches!(argument.kind, ArgumentKind::ExecuteData) {
+            return Err(Error::new(argument.ty.span(), "The variadic argument must be the last one"));
+        }
+        let kind_spec = match argument.kind {
+            ArgumentKind::ExecuteData => continue,
+            ArgumentKind::Native(kind_spec) => kind_spec,
+            ArgumentKind::Reference => "z/",
+            ArgumentKind::Converted => "z",
+            ArgumentKind::Variadic => {
+                has_variadic = true;
+                spec.push('*');
+                continue;
+            },
+        };
+        if argument.optional {
+            if !has_optional {
+                spec.push('|');
+                has_optional = true;
+            }
+            spec.push_str(kind_spec);
+            spec.push('!');
+        } else if has_optional {
+            return Err(Error::new(argument.ty.span(), "Required arguments can't follow optional ones. Use Option"));
+        } else {
+            spec.push_str(kind_spec);
+        }
+    }
+    Ok(spec)
+}
+
+fn parse_arguments(arguments: &[Argument], spec: &str) -> TokenStream {
+    let throw = quote! {
+        ::solder::zend::IntoPhpException::into_php_exception(error).throw();
+        return;
+    };
+    let reads = arguments.iter().map(|argument| {
+        let name = &argument.name;
+        let ty = &argument.ty;
+        match (&argument.kind, argument.optional) {
+            (ArgumentKind::ExecuteData, _) => quote! { let #name: #ty = data; },
+            (ArgumentKind::Native(_), _) | (ArgumentKind::Reference, _) | (ArgumentKind::Variadic, _) => quote! {
+                let #name: #ty = match parameters.next_parameter() {
+                    Ok(value) => value,
+                    Err(_) => return,
+                };
+            },
+            (ArgumentKind::Converted, false) => quote! {
+                let #name: #ty = match parameters.next_parameter::<::solder::zend::Zval>().map(::solder::zend::FromPhpZval::try_from) {
+                    Ok(Ok(value)) => value,
+                    Ok(Err(error)) => { #throw },
+                    Err(_) => return,
+                };
+            },
+            (ArgumentKind::Converted, true) => quote! {
+                let #name: #ty = match parameters.next_parameter::<Option<::solder::zend::Zval>>() {
+                    Ok(Some(zval)) => match ::solder::zend::FromPhpZval::try_from(zval) {
+                        Ok(value) => Some(value),
+                        Err(error) => { #throw },
+                    },
+                    Ok(None) => None,
+                    Err(_) => return,
+                };
+            },
+        }
+    });
+    if spec.is_empty() {
+        return quote! {
+            if ::solder::zend::parse_parameters(data, "").is_err() {
+                return;
+            }
+            #(#reads)*
+        };
+    }
+    quote! {
+        let mut parameters = match ::solder::zend::parse_parameters(data, #spec) {
+            Ok(parameters) => parameters,
+            Err(_) => return,
+        };
+        #(#reads)*
+    }
+}
+
+fn set_return(output: &ReturnType, call: TokenStream) -> TokenStream {
+    let ty = match output {
+        ReturnType::Type(_, ty) if !is_unit(ty) => ty,
+        _ => return quote! { #call; },
+    };
+    let result = known_type(ty, "Result");
+    match result.and_then(generic_argument) {
+        Some(ok) if is_unit(ok) => quote! {
+            if let Err(error) = #call {
+                ::solder::zend::IntoPhpException::into_php_exception(error).throw();
+            }
+        },
+        Some(_) => quote! {
+            match #call {
+                Ok(value) => *retval = ::solder::zend::Zval::from(value),
+                Err(error) => ::solder::zend::IntoPhpException::into_php_exception(error).throw(),
+            }
+        },
+        None => quote! { *retval = ::solder::zend::Zval::from(#call); },
+    }
+}
+
+/// One arginfo for each argument, with the number of required ones
+fn arg_infos(arguments: &[Argument]) -> TokenStream {
+    let required = arguments.iter()
+        .filter(|argument| matches!(argument.kind, ArgumentKind::Native(_) | ArgumentKind::Reference | ArgumentKind::Converted) && !argument.optional)
+        .count() as u32;
+    let arg_infos = arguments.iter()
+        .filter(|argument| !matches!(argument.kind, ArgumentKind::ExecuteData))
+        .map(|argument| {
+            let name = argument.name.to_string();
+            let allow_null = argument.optional as i8;
+            let is_variadic = matches!(argument.kind, ArgumentKind::Variadic) as i8;
+            let by_reference = matches!(argument.kind, ArgumentKind::Reference) as i8;
+            quote! {
+                .with_arg(::solder::zend::ArgInfo::new(concat!(#name, "\0").as_ptr() as *const ::std::os::raw::c_char, #allow_null, #is_variadic, #by_reference))
+            }
+        });
+    quote! {
+        .with_required_args(#required)
+        #(#arg_infos)*
+    }
+}
+
+/// Finds the types handled by the macro, which are recognized by their name. The name must be
+/// used alone, like `Option`, or with its usual path, like `std::option::Option` or
+/// `solder::zend::Zval`. Any other path, like `self::Option`, is a type of your own.
+fn known_type<'a>(ty: &'a Type, name: &str) -> Option<&'a PathSegment> {
+    let path = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    if segment.ident != name {
+        return None;
+    }
+    let prefix: Vec<String> = path.segments.iter().take(path.segments.len() - 1).map(|segment| segment.ident.to_string()).collect();
+    let prefix: Vec<&str> = prefix.iter().map(String::as_str).collect();
+    let paths: &[&[&str]] = match name {
+        "Option" => &[&["std", "option"], &["core", "option"]],
+        "Result" => &[&["std", "result"], &["core", "result"]],
+        "Vec" => &[&["std", "vec"], &["alloc", "vec"]],
+        "String" => &[&["std", "string"], &["alloc", "string"]],
+        "Cow" => &[&["std", "borrow"], &["alloc", "borrow"]],
+        "ExecuteData" | "Zval" | "ZvalRef" | "ZvalMut" => &[&["solder", "zend"], &["zend"]],
+        _ => &[&["std", "primitive"], &["core", "primitive"]],
+    };
+    (prefix.is_empty() || paths.contains(&prefix.as_slice())).then_some(segment)
+}
+
+fn generic_argument(segment: &PathSegment) -> Option<&Type> {
+    match &segment.arguments {
+        PathArguments::AngleBracketed(arguments) => arguments.args.iter().find_map(|argument| match argument {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+fn is_reference_to(ty: &Type, name: &str) -> bool {
+    match ty {
+        Type::Reference(reference) => known_type(&reference.elem, name).is_some(),
+        _ => false,
+    }
+}
+
+fn is_zval(ty: &Type) -> bool {
+    known_type(ty, "Zval").is_some() || known_type(ty, "ZvalRef").is_some()
+}
+
+fn is_unit(ty: &Type) -> bool {
+    matches!(ty, Type::Tuple(tuple) if tuple.elems.is_empty())
+}
@@ -0,0 +1,169 @@
+//! Procedural macros for [solder](https://crates.io/crates/solder).
+//!
+//! They are re-exported by solder, so you don't need to depend on this crate directly.
+
+extern crate proc_macro;
+
+mod class;
+mod function;
+mod module;
+
+use proc_macro::TokenStream;
+
+/// Turns a plain Rust function into a PHP function.
+///
+/// The arguments are parsed and converted from PHP, the return value is converted back and errors
+/// are thrown as exceptions. It keeps the function as it is and generates a `<name>_builder`
+/// function returning the `FunctionBuilder` to give to `ModuleBuilder::with_function`.
+///
+/// ```no_run
+/// # use solder::*;
+/// # use solder::zend::*;
+/// #[php_function]
+/// pub fn hello(name: String, greeting: Option<String>) -> String {
+///     format!("{} {}", greeting.unwrap_or_else(|| "Hello".to_string()), name)
+/// }
+///
+/// # fn get_module() -> *mut Module {
+/// ModuleBuilder::new(c_str!("hello"), c_str!("0.1.0"))
+///     .with_function(hello_builder().build())
+/// #     .build()
+/// #     .into_raw()
+/// # }
+/// ```
+///
+/// Arguments are read according to their types:
+///
+/// - `i64`, `f64`, `bool`, `String`, `Zval` and `ZvalRef` follow PHP's rules like `zend_parse_parameters`
+/// - `&[u8]`, `&str` and `Cow<str>` borrow the string without copying it. `&str`, `Cow<str>` and
+///   `String` throw a `TypeError` if it is not valid UTF-8, so take binary data as `&[u8]` or `Vec<u8>`.
+/// - `ZvalMut` is passed by reference. Setting it changes the variable of the caller.
+/// - any other type is read as a `Zval` and converted with `FromPhpZval`
+/// - `Option<T>` arguments are optional and accept null. They must come after the required ones.
+/// - a last `Vec<Zval>` or `Vec<ZvalRef>` argument receives all remaining arguments
+/// - a `&ExecuteData` argument receives the call frame and is not visible from PHP
+///
+/// The return type must have a `From` conversion to `Zval`. A `Result` is returned when `Ok` and
+/// thrown with `IntoPhpException` when `Err`.
+///
+/// `#[php_module]` adds the function to the module, wherever it is written in the crate.
+///
+/// Macros can't see types, so these are recognized by name. They must be written alone, like
+/// `Option<T>`, or with their usual path, like `std::option::Option<T>` or `solder::zend::Zval`.
+/// Your own types can share those names if they are written with another path, like
+/// `types::Option`, and are then converted with `FromPhpZval` like any other type.
+///
+/// The PHP name defaults to the Rust name and can be changed with `#[php_function(name = "other")]`.
+#[proc_macro_attribute]
+pub fn php_function(args: TokenStream, item: TokenStream) -> TokenStream {
+    function::expand(args.into(), item.into())
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+/// Generates the `get_module` symbol loaded by PHP, on an inline module.
+///
+/// Every `#[php_function]` and `#[php_class]` of the crate is added to it, in any module or file.
+/// Functions of the inline module can be marked with:
+///
+/// - `#[php_info]` to print the module information in `phpinfo()`
+/// - `#[php_startup]` and `#[php_shutdown]` to run when the module starts up and shuts down.
+///   A panic makes them fail.
+/// - `#[php_builder]` on a function taking and returning the `ModuleBuilder`, to declare what
+///   the attributes don't, like globals, INI entries and constants
+///
+/// Panics of these hooks are reported like the ones of handlers.
+///
+/// ```no_run
+/// use solder::*;
+///
+/// #[php_module(name = "hello", version = "0.1.0")]
+/// mod hello {
+///     use solder::*;
+///     use solder::zend::*;
+///     use solder::info::*;
+///
+///     #[php_info]
+///     fn info() {
+///         print_table_start();
+///         print_table_row("hello support", "enabled");
+///         print_table_end();
+///     }
+///
+///     #[php_function]
+///     fn hello(name: String) -> String {
+///         format!("Hello {}", name)
+///     }
+/// }
+/// ```
+///
+/// The name and version default to the ones of the crate.
+#[proc_macro_attribute]
+pub fn php_module(args: TokenStream, item: TokenStream) -> TokenStream {
+    module::expand(args.into(), item.into())
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+/// Marks the function printing the module information. See `php_module`.
+#[proc_macro_attribute]
+pub fn php_info(_args: TokenStream, item: TokenStream) -> TokenStream {
+    module::misplaced_marker("php_info", item.into()).into()
+}
+
+/// Marks the function run when the module starts up. See `php_module`.
+#[proc_macro_attribute]
+pub fn php_startup(_args: TokenStream, item: TokenStream) -> TokenStream {
+    module::misplaced_marker("php_startup", item.into()).into()
+}
+
+/// Marks the function run when the module shuts down. See `php_module`.
+#[proc_macro_attribute]
+pub fn php_shutdown(_args: TokenStream, item: TokenStream) -> TokenStream {
+    module::misplaced_marker("php_shutdown", item.into()).into()
+}
+
+/// Marks the function changing the `ModuleBuilder` before the module is built. See `php_module`.
+#[proc_macro_attribute]
+pub fn php_builder(_args: TokenStream, item: TokenStream) -> TokenStream {
+    module::misplaced_marker("php_builder", item.into()).into()
+}
+
+/// Declares a class, added by `#[php_module]` to the module of the crate.
+///
+/// On a function returning a `Class`, the class is the one it builds with `ClassBuilder`.
+///
+/// On a struct, the objects of the class hold a value of it, created with `Default`. The class
+/// has the name of the struct, or the one given with `name`, and implementing `PhpClass` adds its
+/// parent, properties and methods. With `cloneable`, PHP can clone the objects and the struct must
+/// be `Clone`.
+///
+/// Parents declared in the crate are registered before their children.
+///
+/// ```no_run
+/// # use solder::*;
+/// # use solder::zend::*;
+/// #[php_class(name = "Shop\\Counter")]
+/// #[derive(Default)]
+/// pub struct Counter {
+///     count: i64,
+/// }
+///
+/// fn increment(data: &ExecuteData, retval: &mut Zval) {
+///     let mut counter = data.this_object::<Counter>().unwrap();
+///     counter.count += 1;
+///     php_return!(retval, counter.count);
+/// }
+///
+/// impl PhpClass for Counter {
+///     fn build(class: ClassBuilder) -> ClassBuilder {
+///         class.with_method(FunctionBuilder::new(c_str!("increment"), increment).build())
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn php_class(args: TokenStream, item: TokenStream) -> TokenStream {
+    class::expand(args.into(), item.into())
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
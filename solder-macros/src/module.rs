@@ -0,0 +1,126 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::Parser;
+use syn::spanned::Spanned;
+use syn::{Attribute, Error, Ident, Item, ItemFn, ItemMod, LitStr};
+
+/// The hooks marked in the module. Functions and classes register themselves, wherever they are.
+#[derive(Default)]
+struct ModuleItems {
+    info: Option<Ident>,
+    startup: Option<Ident>,
+    shutdown: Option<Ident>,
+    builder: Option<Ident>,
+}
+
+pub fn expand(args: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    let mut module: ItemMod = syn::parse2(item)?;
+    let (name, version) = parse_name_and_version(args)?;
+    let items = match &mut module.content {
+        Some((_, items)) => items,
+        None => return Err(Error::new(module.span(), "#[php_module] must be used on an inline module")),
+    };
+
+    let mut found = ModuleItems::default();
+    for item in items.iter_mut() {
+        if let Item::Fn(function) = item {
+            collect(function, &mut found)?;
+        }
+    }
+
+    let name = match name {
+        Some(name) => quote! { concat!(#name, "\0") },
+        None => quote! { concat!(env!("CARGO_PKG_NAME"), "\0") },
+    };
+    let version = match version {
+        Some(version) => quote! { concat!(#version, "\0") },
+        None => quote! { concat!(env!("CARGO_PKG_VERSION"), "\0") },
+    };
+    let mut hooks = Vec::new();
+    if let Some(info) = found.info {
+        hooks.push(quote! { .with_info_function(#info) });
+    }
+    if let Some(startup) = found.startup {
+        hooks.push(quote! { .with_startup_hook(#startup) });
+    }
+    if let Some(shutdown) = found.shutdown {
+        hooks.push(quote! { .with_shutdown_hook(#shutdown) });
+    }
+    let mut builder = quote! {
+        ::solder::zend::ModuleBuilder::new(#name.as_ptr() as *const ::std::os::raw::c_char, #version.as_ptr() as *const ::std::os::raw::c_char)
+            #(#hooks)*
+            .with_crate_items(env!("CARGO_CRATE_NAME"))
+    };
+    if let Some(customize) = found.builder {
+        builder = quote! { #customize(#builder) };
+    }
+
+    let get_module: Item = syn::parse2(quote! {
+        const _: () = {
+            #[no_mangle]
+            pub extern "C" fn get_module() -> *mut ::solder::zend::Module {
+                #builder
+                    .build()
+                    .into_raw()
+            }
+        };
+    })?;
+    items.push(get_module);
+    Ok(quote! { #module })
+}
+
+fn parse_name_and_version(args: TokenStream) -> syn::Result<(Option<LitStr>, Option<LitStr>)> {
+    let mut name = None;
+    let mut version = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse::<LitStr>()?);
+            Ok(())
+        } else if meta.path.is_ident("version") {
+            version = Some(meta.value()?.parse::<LitStr>()?);
+            Ok(())
+        } else {
+            Err(meta.error("unsupported php_module property"))
+        }
+    });
+    parser.parse2(args)?;
+    Ok((name, version))
+}
+
+/// Records what a function is used for, removing the marker attributes
+fn collect(function: &mut ItemFn, found: &mut ModuleItems) -> syn::Result<()> {
+    let ident = function.sig.ident.clone();
+    let mut error = None;
+    function.attrs.retain(|attribute| {
+        let slot = if is_attribute(attribute, "php_info") {
+            &mut found.info
+        } else if is_attribute(attribute, "php_startup") {
+            &mut found.startup
+        } else if is_attribute(attribute, "php_shutdown") {
+            &mut found.shutdown
+        } else if is_attribute(attribute, "php_builder") {
+            &mut found.builder
+        } else {
+            return true;
+        };
+        if slot.replace(ident.clone()).is_some() {
+            error = Some(Error::new(attribute.span(), "Only one function can have this attribute"));
+        }
+        false
+    });
+    match error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+fn is_attribute(attribute: &Attribute, name: &str) -> bool {
+    attribute.path().segments.last().is_some_and(|segment| segment.ident == name)
+}
+
+/// Error for a marker attribute used outside of a `#[php_module]`
+pub fn misplaced_marker(name: &str, item: TokenStream) -> TokenStream {
+    let message = format!("#[{}] must be used on a function inside a #[php_module]", name);
+    let error = Error::new(item.span(), message).to_compile_error();
+    quote! { #error #item }
+}
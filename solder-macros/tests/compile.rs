@@ -0,0 +1,5 @@
+#[test]
+fn compile() {
+    let tests = trybuild::TestCases::new();
+    tests.compile_fail("tests/ui/fail/*.rs");
+}
@@ -0,0 +1,68 @@
+//! Functions using the argument types recognized by `#[php_function]`, which must compile.
+//! They can't be called without PHP.
+
+mod known_types {
+    use std::borrow::Cow;
+    use solder::*;
+    use solder::zend::*;
+
+    #[php_function]
+    fn scalars(integer: i64, float: f64, flag: bool, text: String, optional: Option<i64>) -> String {
+        format!("{} {} {} {} {:?}", integer, float, flag, text, optional)
+    }
+
+    #[php_function]
+    fn paths(text: std::string::String, optional: std::option::Option<&str>, rest: std::vec::Vec<Zval>) -> std::result::Result<i64, String> {
+        Ok((text.len() + optional.map_or(0, str::len) + rest.len()) as i64)
+    }
+
+    #[php_function]
+    fn borrowed(data: &ExecuteData, bytes: &[u8], text: Cow<str>, value: ZvalRef, reference: solder::zend::ZvalMut) {
+        let _ = (data, bytes, text, value, reference);
+    }
+
+    #[test]
+    fn builders_are_generated() {
+        let _ = (scalars_builder, paths_builder, borrowed_builder);
+    }
+}
+
+mod own_types {
+    use solder::*;
+
+    /// Types of the extension named like the ones recognized by `#[php_function]`
+    mod types {
+        use solder::zend::*;
+
+        pub struct String(pub i64);
+
+        impl FromPhpZval for String {
+            fn try_from(zval: Zval) -> Result<Self, PhpTypeConversionError> {
+                i64::try_from(zval).map(String)
+            }
+        }
+
+        pub struct Option;
+
+        impl From<Option> for Zval {
+            fn from(_: Option) -> Zval {
+                Zval::new_as_null()
+            }
+        }
+    }
+
+    #[php_function]
+    fn double(value: types::String) -> i64 {
+        value.0 * 2
+    }
+
+    #[php_function]
+    fn nothing() -> types::Option {
+        types::Option
+    }
+
+    #[test]
+    fn builders_are_generated() {
+        let _ = (double_builder, nothing_builder);
+    }
+}
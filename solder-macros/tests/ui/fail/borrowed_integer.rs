@@ -0,0 +1,8 @@
+use solder::*;
+
+#[php_function]
+fn double(value: &i64) -> i64 {
+    value * 2
+}
+
+fn main() {}
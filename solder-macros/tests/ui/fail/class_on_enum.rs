@@ -0,0 +1,10 @@
+use solder::*;
+
+#[php_class]
+#[derive(Default)]
+enum Mode {
+    #[default]
+    Fast,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use solder::*;
+
+#[php_function]
+fn identity<T>(value: T) -> T {
+    value
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use solder::*;
+
+#[php_info]
+fn info() {}
+
+fn main() {}
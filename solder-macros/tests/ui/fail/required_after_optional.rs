@@ -0,0 +1,8 @@
+use solder::*;
+
+#[php_function]
+fn greet(greeting: Option<String>, name: String) -> String {
+    format!("{} {}", greeting.unwrap_or_default(), name)
+}
+
+fn main() {}
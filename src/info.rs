@@ -1,19 +1,52 @@
+//! Module with functions to print a PHPInfo table
+//!
+//! ```no_run
+//! use solder::info::*;
+//! print_table_start();
+//! print_table_row("gpg_keys", "enabled");
+//! print_table_end();
+//! ```
+//!
+//! Tables with headers, sections and more columns are easier to print with `InfoTable`:
+//!
+//! ```no_run
+//! # use solder::info::InfoTable;
+//! InfoTable::new()
+//!     .with_header(&["Cache", "Hits", "Misses"])
+//!     .with_row(&["users", "10", "2"])
+//!     .with_section("Connections")
+//!     .with_row(&["primary", "up", ""])
+//!     .print();
+//! ```
+//!
+//! They are printed as HTML for a web `phpinfo()` and as plain text for `php -i`. In HTML, headers,
+//! rows, section titles and box text are escaped.
+
 use libc::*;
 use std::ffi::CString;
 
-/// Module with functions to print a PHPInfo table
-///
-/// ```
-/// use php::info::*;
-/// print_table_start();
-/// print_table_row("gpg_keys", "enabled");
-/// print_table_end();
-/// ```
+use crate::zend::php_echo;
 
-extern {
+extern "C" {
     pub fn php_info_print_table_start();
-    pub fn php_info_print_table_row(num_cols: c_int, ...) -> c_void;
+    pub fn php_info_print_table_header(num_cols: c_int, ...);
+    pub fn php_info_print_table_row(num_cols: c_int, ...);
+    pub fn php_info_print_table_colspan_header(num_cols: c_int, header: *mut c_char);
     pub fn php_info_print_table_end();
+    pub fn php_info_print_box_start(flag: c_int);
+    pub fn php_info_print_box_end();
+    pub fn php_info_print_hr();
+    static sapi_module: SapiModule;
+}
+
+/// Head of `sapi_module_struct`, up to the field telling how phpinfo is printed
+#[repr(C)]
+struct SapiModule {
+    _names_and_callbacks: [*const c_void; 24],
+    _php_ini_ignore: c_int,
+    _php_ini_ignore_cwd: c_int,
+    _more_callbacks: [*const c_void; 6],
+    phpinfo_as_text: c_int,
 }
 
 /// Starts the PHP Info entry
@@ -23,11 +56,7 @@ pub fn print_table_start() {
 
 /// Print a row with info.
 pub fn print_table_row(key: &str, value: &str) {
-    let v1 = CString::new(key).unwrap();
-    let v2 = CString::new(value).unwrap();
-    unsafe {
-        php_info_print_table_row(2, v1.as_ptr(), v2.as_ptr());
-    };
+    print_table_columns(&[key, value]);
 }
 
 /// Ends the table
@@ -35,3 +64,206 @@ pub fn print_table_end() {
     unsafe { php_info_print_table_end() }
 }
 
+/// Returns if phpinfo is printed as plain text, like with `php -i`, instead of HTML
+pub fn is_text_output() -> bool {
+    unsafe { sapi_module.phpinfo_as_text != 0 }
+}
+
+/// Print a header row with any number of columns
+pub fn print_table_header(columns: &[&str]) {
+    with_c_columns(columns, |pointers| unsafe {
+        match *pointers {
+            [a] => php_info_print_table_header(1, a),
+            [a, b] => php_info_print_table_header(2, a, b),
+            [a, b, c] => php_info_print_table_header(3, a, b, c),
+            [a, b, c, d] => php_info_print_table_header(4, a, b, c, d),
+            [a, b, c, d, e] => php_info_print_table_header(5, a, b, c, d, e),
+            [a, b, c, d, e, f] => php_info_print_table_header(6, a, b, c, d, e, f),
+            [a, b, c, d, e, f, g] => php_info_print_table_header(7, a, b, c, d, e, f, g),
+            [a, b, c, d, e, f, g, h] => php_info_print_table_header(8, a, b, c, d, e, f, g, h),
+            _ => print_wide_columns(columns, true),
+        }
+    });
+}
+
+/// Print a row with any number of columns. The first one is shown as the name of the row.
+pub fn print_table_columns(columns: &[&str]) {
+    with_c_columns(columns, |pointers| unsafe {
+        match *pointers {
+            [a] => php_info_print_table_row(1, a),
+            [a, b] => php_info_print_table_row(2, a, b),
+            [a, b, c] => php_info_print_table_row(3, a, b, c),
+            [a, b, c, d] => php_info_print_table_row(4, a, b, c, d),
+            [a, b, c, d, e] => php_info_print_table_row(5, a, b, c, d, e),
+            [a, b, c, d, e, f] => php_info_print_table_row(6, a, b, c, d, e, f),
+            [a, b, c, d, e, f, g] => php_info_print_table_row(7, a, b, c, d, e, f, g),
+            [a, b, c, d, e, f, g, h] => php_info_print_table_row(8, a, b, c, d, e, f, g, h),
+            _ => print_wide_columns(columns, false),
+        }
+    });
+}
+
+/// Print a header spanning `num_cols` columns, to split a table in sections
+pub fn print_table_colspan_header(num_cols: usize, header: &str) {
+    // Unlike other headers, the engine prints this one as it is
+    let header = if is_text_output() { c_string(header) } else { c_string(&escape_html(header)) };
+    unsafe { php_info_print_table_colspan_header(num_cols as c_int, header.as_ptr() as *mut c_char) };
+}
+
+/// Starts a box for free text. A highlighted box has the colors of the table headers.
+pub fn print_box_start(highlighted: bool) {
+    unsafe { php_info_print_box_start(highlighted as c_int) };
+}
+
+/// Print text inside a box
+pub fn print_box_text(text: &str) {
+    if is_text_output() {
+        php_echo(text);
+        php_echo("\n");
+    } else {
+        php_echo(&escape_html(text));
+    }
+}
+
+/// Ends the box
+pub fn print_box_end() {
+    unsafe { php_info_print_box_end() };
+}
+
+/// Print a horizontal line between boxes or tables
+pub fn print_hr() {
+    unsafe { php_info_print_hr() };
+}
+
+enum InfoLine {
+    Header(Vec<String>),
+    Row(Vec<String>),
+    Section(String),
+}
+
+/// Builder of a PHP Info table with headers, rows of any number of columns and sections
+#[derive(Default)]
+pub struct InfoTable {
+    lines: Vec<InfoLine>,
+}
+
+impl InfoTable {
+    /// Create an empty table
+    pub fn new() -> Self {
+        InfoTable::default()
+    }
+
+    /// Add a header row
+    pub fn with_header<S: AsRef<str>>(mut self, columns: &[S]) -> Self {
+        self.lines.push(InfoLine::Header(columns.iter().map(|column| column.as_ref().to_string()).collect()));
+        self
+    }
+
+    /// Add a row. The first column is shown as its name.
+    pub fn with_row<S: AsRef<str>>(mut self, columns: &[S]) -> Self {
+        self.lines.push(InfoLine::Row(columns.iter().map(|column| column.as_ref().to_string()).collect()));
+        self
+    }
+
+    /// Add a header spanning the whole table, starting a new section
+    pub fn with_section(mut self, title: &str) -> Self {
+        self.lines.push(InfoLine::Section(title.to_string()));
+        self
+    }
+
+    /// Print the table
+    pub fn print(&self) {
+        let num_cols = self.lines.iter()
+            .map(|line| match line {
+                InfoLine::Header(columns) | InfoLine::Row(columns) => columns.len(),
+                InfoLine::Section(_) => 1,
+            })
+            .max()
+            .unwrap_or(1);
+        print_table_start();
+        for line in &self.lines {
+            match line {
+                InfoLine::Header(columns) => print_table_header(&columns.iter().map(String::as_str).collect::<Vec<_>>()),
+                InfoLine::Row(columns) => print_table_columns(&columns.iter().map(String::as_str).collect::<Vec<_>>()),
+                InfoLine::Section(title) => print_table_colspan_header(num_cols, title),
+            }
+        }
+        print_table_end();
+    }
+}
+
+/// The engine prints up to 8 columns in one call, wider headers and rows are printed like it does
+fn print_wide_columns(columns: &[&str], header: bool) {
+    let text = is_text_output();
+    let mut output = String::new();
+    if !text {
+        output.push_str(if header { "<tr class=\"h\">" } else { "<tr>" });
+    }
+    for (index, column) in columns.iter().enumerate() {
+        let last = index == columns.len() - 1;
+        if text {
+            // Like the engine, an empty value of a row is not followed by a separator
+            if column.is_empty() {
+                output.push(' ');
+            } else {
+                output.push_str(column);
+            }
+            if !last && (header || !column.is_empty()) {
+                output.push_str(" => ");
+            }
+            if last {
+                output.push('\n');
+            }
+            continue;
+        }
+        output.push_str(if header { "<th>" } else if index == 0 { "<td class=\"e\">" } else { "<td class=\"v\">" });
+        match (column.is_empty(), header) {
+            (true, true) => output.push(' '),
+            (true, false) => output.push_str("<i>no value</i>"),
+            (false, _) => output.push_str(&escape_html(column)),
+        }
+        output.push_str(if header { "</th>" } else { " </td>" });
+    }
+    if !text {
+        output.push_str("</tr>\n");
+    }
+    php_echo(&output);
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#039;"),
+            character => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+/// Gives the columns to `print` as C strings, which live until it returns
+fn with_c_columns(columns: &[&str], print: impl FnOnce(&[*const c_char])) {
+    let columns: Vec<CString> = columns.iter().map(|column| c_string(column)).collect();
+    let pointers: Vec<*const c_char> = columns.iter().map(|column| column.as_ptr()).collect();
+    print(&pointers);
+}
+
+/// PHP strings end at the first NUL, so the ones inside the text are dropped
+fn c_string(text: &str) -> CString {
+    CString::new(text.replace('\0', "")).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_special_characters_are_escaped() {
+        assert_eq!(escape_html("<a href=\"x\">Tom & Jerry's</a>"), "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#039;s&lt;/a&gt;");
+        assert_eq!(escape_html("plain"), "plain");
+    }
+}
@@ -9,7 +9,7 @@
 //!
 //! Example:
 //!
-//! ```rust
+//! ```no_run
 //! extern crate libc;
 //! extern crate solder;
 //!
@@ -17,8 +17,7 @@
 //! use solder::zend::*;
 //! use solder::info::*;
 //!
-//! #[no_mangle]
-//! pub extern fn php_module_info() {
+//! fn php_module_info() {
 //!     print_table_start();
 //!     print_table_row("A demo PHP extension written in Rust", "enabled");
 //!     print_table_end();
@@ -37,25 +36,22 @@
 //! }
 //!
 //!
-//! #[no_mangle]
-//! pub extern fn hello_world(_data: &ExecuteData, retval: &mut Zval) {
+//! pub fn hello_world(data: &ExecuteData, retval: &mut Zval) {
 //!     let mut name_zval = Zval::new_as_null();
-//!     php_parse_parameters!(&mut name_zval);
-//!     let name = String::try_from(name_zval).ok().unwrap();
-//!     let hello = format!("Hello {}", name);
-//!     php_return!(retval, hello);
+//!     php_parse_parameters!(data, &mut name_zval);
+//!     php_return_result!(retval, String::try_from(name_zval).map(|name| format!("Hello {}", name)));
 //! }
 //! ```
 //!
 //! To compile it, we need to add to our `.cargo/config`:
-//! ```
+//! ```toml
 //! [build]
 //! rustflags = ["-C", "link-arg=-Wl,-undefined,dynamic_lookup"]
 //! ```
 //!
 //! Than, you compile the extension using `cargo build` and load it copying it to your PHP modules dir and modifying you `php.ini`.
 //!
-//! ```
+//! ```text
 //! $ cargo build && php -dextension=$(pwd)/target/debug/libhelloworld.so -a
 //!    Compiling solder v0.1.0 (/src)
 //!    Compiling helloworld v0.1.0 (/src/examples/helloworld)
@@ -72,8 +68,15 @@
 //!
 //! If you have questions or ideas to the project. Feel free to contact me.
 
+
 extern crate libc;
 
+pub use solder_macros::{php_function, php_module, php_info, php_startup, php_shutdown, php_builder, php_class};
+
+// Used by the attribute macros to collect the functions and classes of a crate
+#[doc(hidden)]
+pub use inventory;
+
 #[macro_use]
 pub mod macros;
 pub mod zend;
@@ -0,0 +1,260 @@
+use std::{mem, slice};
+use libc::*;
+
+use super::internal_php_methods::*;
+use super::types::*;
+
+/// Key of an array element. String keys are kept as bytes because PHP strings are binary safe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrayKey<'a> {
+	Int(i64),
+	Str(&'a [u8]),
+}
+
+impl From<i64> for ArrayKey<'_> {
+	fn from(key: i64) -> Self {
+		ArrayKey::Int(key)
+	}
+}
+
+impl<'a> From<&'a str> for ArrayKey<'a> {
+	fn from(key: &'a str) -> Self {
+		ArrayKey::Str(key.as_bytes())
+	}
+}
+
+impl<'a> From<&'a [u8]> for ArrayKey<'a> {
+	fn from(key: &'a [u8]) -> Self {
+		ArrayKey::Str(key)
+	}
+}
+
+/// A PHP array (`zend_array`).
+///
+/// An owned `PhpArray` holds one reference to the table and releases it when dropped.
+/// Arrays inside a zval are borrowed with `Zval::as_array` and `Zval::as_array_mut`.
+///
+/// String keys are used as they are, so `"1"` and `1` are different keys here, while PHP code
+/// would turn `"1"` into `1`.
+#[repr(transparent)]
+pub struct PhpArray {
+	array: *mut ZendArray,
+}
+
+impl PhpArray {
+	/// Creates an empty array
+	pub fn new() -> Self {
+		let mut zval = Zval::new_as_null();
+		create_zend_array(&mut zval);
+		let array = PhpArray { array: unsafe { zval.value.array } };
+		// The reference of the zval now belongs to the PhpArray
+		mem::forget(zval);
+		array
+	}
+
+	/// Returns the number of elements
+	pub fn len(&self) -> usize {
+		unsafe { (*self.array).n_num_of_elements as usize }
+	}
+
+	/// Returns if the array has no elements
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Returns if the array is packed, a list with only integer keys stored without a hash
+	pub fn is_packed(&self) -> bool {
+		unsafe { (*self.array).flags & HASH_FLAG_PACKED != 0 }
+	}
+
+	/// Borrows the value stored with key
+	pub fn get<'k>(&self, key: impl Into<ArrayKey<'k>>) -> Option<ZvalRef<'_>> {
+		let value = self.find(key.into());
+		if value.is_null() {
+			return None;
+		}
+		unsafe { Some(ZvalRef::from_ptr(value)) }
+	}
+
+	/// Borrows the value stored with key to change it
+	pub fn get_mut<'k>(&mut self, key: impl Into<ArrayKey<'k>>) -> Option<ZvalMut<'_>> {
+		let value = self.find(key.into());
+		if value.is_null() {
+			return None;
+		}
+		unsafe { Some(ZvalMut::from_ptr(value)) }
+	}
+
+	fn find(&self, key: ArrayKey) -> *mut Zval {
+		unsafe {
+			match key {
+				ArrayKey::Int(index) => zend_hash_index_find(self.array, index as u64),
+				ArrayKey::Str(key) => zend_hash_str_find(self.array, key.as_ptr() as *const c_char, key.len()),
+			}
+		}
+	}
+
+	/// Returns if there is a value stored with key
+	pub fn contains_key<'k>(&self, key: impl Into<ArrayKey<'k>>) -> bool {
+		self.get(key).is_some()
+	}
+
+	/// Stores a value with key, replacing and releasing the previous one
+	pub fn insert<'k, T>(&mut self, key: impl Into<ArrayKey<'k>>, value: T)
+		where Zval: From<T>
+	{
+		let mut value = Zval::from(value);
+		unsafe {
+			match key.into() {
+				ArrayKey::Int(index) => hash_index_update(self.array, index as u64, &mut value),
+				ArrayKey::Str(key) => hash_str_update(self.array, key.as_ptr() as *const c_char, key.len(), &mut value),
+			};
+		}
+		// The array owns the value now
+		mem::forget(value);
+	}
+
+	/// Appends a value using the next free integer key
+	pub fn push<T>(&mut self, value: T)
+		where Zval: From<T>
+	{
+		let mut value = Zval::from(value);
+		unsafe {
+			hash_next_index_insert(self.array, &mut value);
+		}
+		mem::forget(value);
+	}
+
+	/// Removes and releases the value stored with key. Returns if there was one.
+	pub fn remove<'k>(&mut self, key: impl Into<ArrayKey<'k>>) -> bool {
+		let result = unsafe {
+			match key.into() {
+				ArrayKey::Int(index) => zend_hash_index_del(self.array, index as u64),
+				ArrayKey::Str(key) => zend_hash_str_del(self.array, key.as_ptr() as *const c_char, key.len()),
+			}
+		};
+		result == 0
+	}
+
+	/// Iterates over the keys and values in order
+	pub fn iter(&self) -> ArrayIter<'_> {
+		let buckets = unsafe {
+			let used = (*self.array).n_num_used as usize;
+			if used == 0 {
+				&[]
+			} else {
+				slice::from_raw_parts((*self.array).array_data, used)
+			}
+		};
+		ArrayIter { buckets: buckets.iter() }
+	}
+
+	/// Gives the array to a zval
+	pub fn into_zval(self) -> Zval {
+		let mut zval = Zval::new_as_null();
+		zval.value.array = self.array;
+		// Immutable arrays are not reference counted, so the zval must not release them
+		zval.type_info.type_info = if self.is_immutable() { InternalPhpTypes::ARRAY as u32 } else { IS_ARRAY_EX };
+		mem::forget(self);
+		zval
+	}
+
+	/// Returns if the array is immutable, shared by every request without being reference counted
+	fn is_immutable(&self) -> bool {
+		unsafe { ((*self.array).gc.type_info >> GC_FLAGS_SHIFT) & GC_IMMUTABLE != 0 }
+	}
+}
+
+impl Default for PhpArray {
+	fn default() -> Self {
+		PhpArray::new()
+	}
+}
+
+/// Releases the reference through a zval, so the engine destroys the array or collects its cycles
+impl Drop for PhpArray {
+	fn drop(&mut self) {
+		drop(PhpArray { array: self.array }.into_zval());
+	}
+}
+
+impl From<PhpArray> for Zval {
+	fn from(array: PhpArray) -> Self {
+		array.into_zval()
+	}
+}
+
+impl<'a> IntoIterator for &'a PhpArray {
+	type Item = (ArrayKey<'a>, ZvalRef<'a>);
+	type IntoIter = ArrayIter<'a>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
+/// Iterator over the elements of a `PhpArray`
+pub struct ArrayIter<'a> {
+	buckets: slice::Iter<'a, Bucket>,
+}
+
+impl<'a> Iterator for ArrayIter<'a> {
+	type Item = (ArrayKey<'a>, ZvalRef<'a>);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		for bucket in self.buckets.by_ref() {
+			let mut value = &bucket.value;
+			// Symbol tables store pointers to the real values
+			if value.type_info.is_from_type(InternalPhpTypes::INDIRECT) {
+				value = unsafe { &*value.value.zval };
+			}
+			if value.is_undef() {
+				continue;
+			}
+			let key = if bucket.key.is_null() {
+				ArrayKey::Int(bucket.hash as i64)
+			} else {
+				ArrayKey::Str(unsafe { slice::from_raw_parts((*bucket.key).value.as_ptr(), (*bucket.key).len) })
+			};
+			return Some((key, unsafe { ZvalRef::from_ptr(value) }));
+		}
+		None
+	}
+}
+
+impl Zval {
+	/// Borrows the array stored in this zval
+	pub fn as_array(&self) -> Option<&PhpArray> {
+		if !self.is_array() {
+			return None;
+		}
+		unsafe { Some(&*(&self.value.array as *const *mut ZendArray as *const PhpArray)) }
+	}
+
+	/// Borrows the array stored in this zval to change it.
+	/// If the array is shared with other zvals, it is copied first, like PHP does on write.
+	pub fn as_array_mut(&mut self) -> Option<&mut PhpArray> {
+		if !self.is_array() {
+			return None;
+		}
+		self.separate_array();
+		unsafe { Some(&mut *(&mut self.value.array as *mut *mut ZendArray as *mut PhpArray)) }
+	}
+
+	/// Gives the zval its own copy of an array shared with other zvals, like `SEPARATE_ARRAY`
+	pub(crate) fn separate_array(&mut self) {
+		if !self.is_array() {
+			return;
+		}
+		unsafe {
+			let array = self.value.array;
+			if (*array).gc.ref_count > 1 || self.as_array().is_some_and(PhpArray::is_immutable) {
+				let mut shared = PhpArray { array: zend_array_dup(array) }.into_zval();
+				// Only the value is swapped, `u2` may chain the zval in a hash table
+				mem::swap(&mut self.value, &mut shared.value);
+				mem::swap(&mut self.type_info, &mut shared.type_info);
+				drop(shared);
+			}
+		}
+	}
+}
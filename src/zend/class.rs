@@ -0,0 +1,387 @@
+use std::any::TypeId;
+use std::cell::{RefCell, RefMut};
+use std::ffi::CStr;
+use std::sync::Mutex;
+use std::{mem, ptr};
+use libc::*;
+
+use super::internal_php_methods::*;
+use super::module::{Function, ACC_PPP_MASK, ACC_PUBLIC};
+use super::panic::{catch_panic, raise_pending_fatal_error};
+use super::types::*;
+use super::string::PhpString;
+
+type CreateObjectFunc = extern "C" fn (class_entry: *mut ClassEntry) -> *mut ZendObject;
+type FreeObjectFunc = extern "C" fn (object: *mut ZendObject);
+type CloneObjectFunc = extern "C" fn (object: *mut Zval) -> *mut ZendObject;
+
+/// Internal representation of a class inside the engine (`zend_class_entry`)
+#[repr(C)]
+pub struct ClassEntry {
+	type_: c_char,
+	pub(crate) name: *mut ZendString,
+	parent: *mut ClassEntry,
+	refcount: c_int,
+	ce_flags: u32,
+	default_properties_count: c_int,
+	default_static_members_count: c_int,
+	default_properties_table: *mut Zval,
+	default_static_members_table: *mut Zval,
+	static_members_table: *mut Zval,
+	function_table: ZendArray,
+	properties_info: ZendArray,
+	constants_table: ZendArray,
+	magic_methods: [*mut c_void; 13],
+	#[cfg(feature = "php73")]
+	iterator_funcs_ptr: *mut c_void,
+	#[cfg(not(feature = "php73"))]
+	iterator_funcs: [*mut c_void; 7],
+	create_object: Option<CreateObjectFunc>,
+	get_iterator: *mut c_void,
+	#[cfg(not(feature = "php73"))]
+	interface_gets_implemented: *mut c_void,
+	get_static_method: *mut c_void,
+	serialize: *mut c_void,
+	unserialize: *mut c_void,
+	num_interfaces: u32,
+	num_traits: u32,
+	interfaces: *mut *mut ClassEntry,
+	traits: *mut *mut ClassEntry,
+	trait_aliases: *mut c_void,
+	trait_precedences: *mut c_void,
+	builtin_functions: *const Function,
+	module: *mut c_void,
+	info_padding: *mut c_void,
+}
+
+/// Handlers used by the engine to operate on objects (`zend_object_handlers`)
+#[repr(C)]
+pub struct ObjectHandlers {
+	offset: c_int,
+	free_obj: Option<FreeObjectFunc>,
+	dtor_obj: *const c_void,
+	clone_obj: Option<CloneObjectFunc>,
+	others: [*const c_void; 24],
+}
+
+unsafe impl Sync for ObjectHandlers {}
+
+/// Memory layout of an object that holds a Rust struct.
+/// The zend_object must be the last field because the engine allocates the properties after it.
+#[repr(C)]
+struct ObjectWrapper<T> {
+	state: RefCell<T>,
+	std: ZendObject,
+}
+
+/// Handlers of each Rust struct used as object state, and whether they clone it, created on demand
+static OBJECT_HANDLERS: Mutex<Vec<(TypeId, bool, &'static ObjectHandlers)>> = Mutex::new(Vec::new());
+
+/// Class entries registered by this module, indexed by their lowercase name
+static REGISTERED_CLASSES: Mutex<Vec<(String, usize)>> = Mutex::new(Vec::new());
+
+/// A class ready to be registered by the module
+pub struct Class {
+	name: *const c_char,
+	parent: Option<*const c_char>,
+	methods: Vec<Function>,
+	properties: Vec<(*const c_char, Zval, u32)>,
+	create_object: Option<CreateObjectFunc>,
+}
+
+unsafe impl Send for Class {}
+
+impl Class {
+	pub(crate) fn name(&self) -> &str {
+		c_name_to_str(self.name)
+	}
+
+	pub(crate) fn parent_name(&self) -> Option<&str> {
+		self.parent.map(c_name_to_str)
+	}
+
+	pub(crate) fn register(self) -> *mut ClassEntry {
+		let name = c_name_to_str(self.name);
+		let parent = match self.parent {
+			Some(parent) => {
+				let parent = c_name_to_str(parent);
+				find_class(parent).unwrap_or_else(|| panic!("Parent class {} of {} was not found", parent, name))
+			},
+			None => ptr::null_mut(),
+		};
+		let mut methods = self.methods;
+		methods.push(Function::end());
+		unsafe {
+			let mut class_entry: ClassEntry = mem::zeroed();
+			class_entry.name = PhpString::interned(name).into_raw();
+			class_entry.builtin_functions = Box::into_raw(methods.into_boxed_slice()) as *const Function;
+			let registered = zend_register_internal_class_ex(&mut class_entry, parent);
+			if let Some(create_object) = self.create_object {
+				(*registered).create_object = Some(create_object);
+			}
+			for (property_name, default, flags) in self.properties {
+				declare_property(registered, property_name, default, flags);
+			}
+			REGISTERED_CLASSES.lock().unwrap().push((name.to_lowercase(), registered as usize));
+			registered
+		}
+	}
+}
+
+fn c_name_to_str<'a>(name: *const c_char) -> &'a str {
+	unsafe { CStr::from_ptr(name) }.to_str().expect("Class and property names must be valid UTF-8")
+}
+
+/// Declares a property with a default value. Internal classes only accept scalar defaults.
+fn declare_property(class_entry: *mut ClassEntry, name: *const c_char, default: Zval, flags: u32) {
+	let name_length = c_name_to_str(name).len();
+	let flags = flags as c_int;
+	unsafe {
+		if default.is_null() {
+			zend_declare_property_null(class_entry, name, name_length, flags);
+		} else if default.is_integer() {
+			zend_declare_property_long(class_entry, name, name_length, default.value.long_value, flags);
+		} else if default.is_float() {
+			zend_declare_property_double(class_entry, name, name_length, default.value.double_value, flags);
+		} else if default.type_info.is_from_type(InternalPhpTypes::TRUE) {
+			zend_declare_property_bool(class_entry, name, name_length, 1, flags);
+		} else if default.type_info.is_from_type(InternalPhpTypes::FALSE) {
+			zend_declare_property_bool(class_entry, name, name_length, 0, flags);
+		} else if default.is_string() {
+			let string = default.value.string;
+			zend_declare_property_stringl(class_entry, name, name_length, (*string).value.as_ptr() as *const c_char, (*string).len, flags);
+		} else {
+			panic!("Property {} must have a null, bool, integer, float or string default", c_name_to_str(name));
+		}
+	}
+}
+
+pub struct ClassBuilder {
+	class: Class,
+}
+
+impl ClassBuilder {
+	/// Create a class with name
+	pub fn new(name: *const c_char) -> Self {
+		ClassBuilder {
+			class: Class {
+				name,
+				parent: None,
+				methods: Vec::new(),
+				properties: Vec::new(),
+				create_object: None,
+			},
+		}
+	}
+
+	/// Extend a class. The parent must be declared by this module, which registers it first, or be
+	/// one of the builtin exceptions known by `find_class`. Otherwise the module fails to start.
+	pub fn with_parent(mut self, parent: *const c_char) -> Self {
+		self.class.parent = Some(parent);
+		self
+	}
+
+	/// Declare a property with a default value. Use `ACC_PUBLIC`, `ACC_PROTECTED` or `ACC_PRIVATE`
+	/// as flags. The default must be null, bool, integer, float or string.
+	pub fn with_property<T>(mut self, name: *const c_char, default: T, flags: u32) -> Self
+		where Zval: From<T>
+	{
+		self.class.properties.push((name, Zval::from(default), flags));
+		self
+	}
+
+	/// Attach a Rust struct to every object of this class. It is created with `Default` when
+	/// the object is instantiated and dropped when PHP frees it.
+	/// Methods can access it using `ExecuteData::this_object`.
+	///
+	/// Objects of this class can't be cloned, `clone` throws an `\Error`. Use
+	/// `with_cloneable_state` to allow it.
+	pub fn with_state<T: Default + 'static>(mut self) -> Self {
+		assert!(mem::align_of::<T>() <= 8, "Object state can't be aligned to more than 8 bytes");
+		self.class.create_object = Some(create_object::<T>);
+		self
+	}
+
+	/// Like `with_state`, but PHP can `clone` the objects. The clone gets a `Clone` of the Rust
+	/// struct and a copy of the properties, like any PHP object.
+	pub fn with_cloneable_state<T: Default + Clone + 'static>(mut self) -> Self {
+		assert!(mem::align_of::<T>() <= 8, "Object state can't be aligned to more than 8 bytes");
+		self.class.create_object = Some(create_cloneable_object::<T>);
+		self
+	}
+
+	/// Add a method to the class. Methods without visibility flags are public.
+	pub fn with_method(mut self, mut method: Function) -> Self {
+		if method.flags & ACC_PPP_MASK == 0 {
+			method.flags |= ACC_PUBLIC;
+		}
+		self.class.methods.push(method);
+		self
+	}
+
+	/// Build the class
+	pub fn build(self) -> Class {
+		self.class
+	}
+}
+
+/// Class of a type annotated with `#[php_class]`, whose objects hold a value of the type as their
+/// state, see `ClassBuilder::with_state`.
+pub trait PhpClass: Default + 'static {
+	/// Add the parent, properties and methods of the class, which only has the state by default
+	fn build(class: ClassBuilder) -> ClassBuilder {
+		class
+	}
+}
+
+impl ExecuteData {
+	/// Borrows the Rust struct attached to `$this`.
+	/// Returns `None` if this is not a method call or if `$this` holds a different struct.
+	///
+	/// The struct is borrowed like a `RefCell`. It panics if it is already borrowed, e.g. when a
+	/// method calls back into PHP, which calls another method on the same object.
+	pub fn this_object<T: 'static>(&self) -> Option<RefMut<'_, T>> {
+		let this = self.this()?;
+		unsafe {
+			let object = this.value.object;
+			if !has_state::<T>(object) {
+				return None;
+			}
+			Some((*wrapper_from_object::<T>(object)).state.borrow_mut())
+		}
+	}
+}
+
+/// Returns the class entry of a class registered by this module or of one of the builtin
+/// exception and error classes (`Exception`, `TypeError`, `RuntimeException`, ...)
+pub fn find_class(name: &str) -> Option<*mut ClassEntry> {
+	find_registered_class(name).or_else(|| find_core_class(name))
+}
+
+/// Returns the class entry of a class registered by this module
+pub fn find_registered_class(name: &str) -> Option<*mut ClassEntry> {
+	let name = name.trim_start_matches('\\').to_lowercase();
+	REGISTERED_CLASSES.lock().unwrap()
+		.iter()
+		.find(|(registered_name, _)| *registered_name == name)
+		.map(|(_, class_entry)| *class_entry as *mut ClassEntry)
+}
+
+fn find_core_class(name: &str) -> Option<*mut ClassEntry> {
+	let name = name.trim_start_matches('\\').to_lowercase();
+	let class_entry = unsafe {
+		match name.as_str() {
+			"exception" => zend_ce_exception,
+			"errorexception" => zend_ce_error_exception,
+			"error" => zend_ce_error,
+			"typeerror" => zend_ce_type_error,
+			"argumentcounterror" => zend_ce_argument_count_error,
+			"arithmeticerror" => zend_ce_arithmetic_error,
+			"divisionbyzeroerror" => zend_ce_division_by_zero_error,
+			"logicexception" => spl_ce_LogicException,
+			"badfunctioncallexception" => spl_ce_BadFunctionCallException,
+			"badmethodcallexception" => spl_ce_BadMethodCallException,
+			"domainexception" => spl_ce_DomainException,
+			"invalidargumentexception" => spl_ce_InvalidArgumentException,
+			"lengthexception" => spl_ce_LengthException,
+			"outofrangeexception" => spl_ce_OutOfRangeException,
+			"runtimeexception" => spl_ce_RuntimeException,
+			"outofboundsexception" => spl_ce_OutOfBoundsException,
+			"overflowexception" => spl_ce_OverflowException,
+			"rangeexception" => spl_ce_RangeException,
+			"underflowexception" => spl_ce_UnderflowException,
+			"unexpectedvalueexception" => spl_ce_UnexpectedValueException,
+			_ => return None,
+		}
+	};
+	Some(class_entry)
+}
+
+fn object_handlers<T: 'static>(clone_obj: Option<CloneObjectFunc>) -> &'static ObjectHandlers {
+	let cloneable = clone_obj.is_some();
+	let mut registered_handlers = OBJECT_HANDLERS.lock().unwrap();
+	let registered = registered_handlers.iter()
+		.find(|(type_id, registered_cloneable, _)| *type_id == TypeId::of::<T>() && *registered_cloneable == cloneable);
+	if let Some((_, _, handlers)) = registered {
+		return handlers;
+	}
+	let mut handlers = unsafe { ptr::read(&std_object_handlers) };
+	handlers.offset = mem::offset_of!(ObjectWrapper<T>, std) as c_int;
+	handlers.free_obj = Some(free_object::<T>);
+	handlers.clone_obj = clone_obj;
+	let handlers: &'static ObjectHandlers = Box::leak(Box::new(handlers));
+	registered_handlers.push((TypeId::of::<T>(), cloneable, handlers));
+	handlers
+}
+
+/// Whether the object was created with `T` as state
+unsafe fn has_state<T: 'static>(object: *mut ZendObject) -> bool {
+	OBJECT_HANDLERS.lock().unwrap()
+		.iter()
+		.any(|(type_id, _, handlers)| *type_id == TypeId::of::<T>() && ptr::eq(*handlers, (*object).handlers))
+}
+
+unsafe fn wrapper_from_object<T>(object: *mut ZendObject) -> *mut ObjectWrapper<T> {
+	(object as *mut u8).sub(mem::offset_of!(ObjectWrapper<T>, std)) as *mut ObjectWrapper<T>
+}
+
+extern "C" fn create_object<T: Default + 'static>(class_entry: *mut ClassEntry) -> *mut ZendObject {
+	let object = new_object(class_entry, catch_panic(T::default), || object_handlers::<T>(None));
+	raise_pending_fatal_error();
+	object
+}
+
+extern "C" fn create_cloneable_object<T: Default + Clone + 'static>(class_entry: *mut ClassEntry) -> *mut ZendObject {
+	let object = new_object(class_entry, catch_panic(T::default), || object_handlers::<T>(Some(clone_object::<T>)));
+	raise_pending_fatal_error();
+	object
+}
+
+/// Allocates an object holding `state`. If creating the state panicked, the object is a plain
+/// one without state, so the engine still gets an object while the panic is reported.
+fn new_object<T: 'static>(class_entry: *mut ClassEntry, state: Option<T>, handlers: impl FnOnce() -> &'static ObjectHandlers) -> *mut ZendObject {
+	unsafe {
+		let object = match state {
+			Some(state) => allocate_object(class_entry, state, handlers()),
+			None => zend_objects_new(class_entry),
+		};
+		object_properties_init(object, class_entry);
+		object
+	}
+}
+
+/// Allocates the wrapper of an object with room for its properties, which are left to initialize
+unsafe fn allocate_object<T>(class_entry: *mut ClassEntry, state: T, handlers: *const ObjectHandlers) -> *mut ZendObject {
+	let properties_size = mem::size_of::<Zval>() * (*class_entry).default_properties_count as usize;
+	let wrapper = _ecalloc(1, mem::size_of::<ObjectWrapper<T>>() + properties_size) as *mut ObjectWrapper<T>;
+	ptr::write(&mut (*wrapper).state, RefCell::new(state));
+	let object = &mut (*wrapper).std as *mut ZendObject;
+	zend_object_std_init(object, class_entry);
+	(*object).handlers = handlers;
+	object
+}
+
+extern "C" fn clone_object<T: Clone + 'static>(zval: *mut Zval) -> *mut ZendObject {
+	let object = unsafe {
+		let old_object = (*zval).value.object;
+		let class_entry = (*old_object).ce;
+		let state = catch_panic(|| (*wrapper_from_object::<T>(old_object)).state.borrow().clone());
+		let new_object = match state {
+			Some(state) => allocate_object(class_entry, state, (*old_object).handlers),
+			None => zend_objects_new(class_entry),
+		};
+		zend_objects_clone_members(new_object, old_object);
+		new_object
+	};
+	raise_pending_fatal_error();
+	object
+}
+
+extern "C" fn free_object<T>(object: *mut ZendObject) {
+	unsafe {
+		let state = &mut (*wrapper_from_object::<T>(object)).state;
+		catch_panic(|| ptr::drop_in_place(state));
+		zend_object_std_dtor(object);
+	}
+	raise_pending_fatal_error();
+}
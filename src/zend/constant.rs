@@ -0,0 +1,164 @@
+use std::ffi::CStr;
+use libc::*;
+
+use super::internal_php_methods::*;
+
+/// The constant name is case sensitive. Case insensitive constants are deprecated since PHP 7.3.
+pub const CONST_CS: c_int = 1;
+/// The constant outlives the requests. Always set for module constants.
+pub const CONST_PERSISTENT: c_int = 2;
+
+/// Types that a constant can hold: null, bools, integers, floats and strings.
+/// `Option` gives null for `None`. It can't be implemented outside of solder.
+pub trait ConstantValue: sealed::IntoValue {}
+
+mod sealed {
+	/// Value of a constant, copied so it can wait until the module starts up
+	#[derive(Debug, PartialEq)]
+	pub enum Value {
+		Null,
+		Bool(bool),
+		Integer(i64),
+		Float(f64),
+		String(Vec<u8>),
+	}
+
+	pub trait IntoValue {
+		fn into_value(self) -> Value;
+	}
+}
+
+use self::sealed::{IntoValue, Value};
+
+macro_rules! constant_value {
+	($($type_:ty => |$value:ident| $conversion:expr),* $(,)?) => {
+		$(
+			impl IntoValue for $type_ {
+				fn into_value(self) -> Value {
+					let $value = self;
+					$conversion
+				}
+			}
+
+			impl ConstantValue for $type_ {}
+		)*
+	};
+}
+
+constant_value! {
+	() => |_value| Value::Null,
+	bool => |value| Value::Bool(value),
+	i64 => |value| Value::Integer(value),
+	i32 => |value| Value::Integer(value.into()),
+	u32 => |value| Value::Integer(value.into()),
+	f64 => |value| Value::Float(value),
+	&str => |value| Value::String(value.as_bytes().to_vec()),
+	String => |value| Value::String(value.into_bytes()),
+	&[u8] => |value| Value::String(value.to_vec()),
+	Vec<u8> => |value| Value::String(value),
+}
+
+impl<T: ConstantValue> IntoValue for Option<T> {
+	fn into_value(self) -> Value {
+		self.map_or(Value::Null, T::into_value)
+	}
+}
+
+impl<T: ConstantValue> ConstantValue for Option<T> {}
+
+/// A constant of the module, registered when it starts up
+pub struct Constant {
+	name: String,
+	value: Value,
+	flags: c_int,
+}
+
+pub struct ConstantBuilder {
+	namespace: Option<*const c_char>,
+	name: *const c_char,
+	value: Value,
+	flags: c_int,
+}
+
+impl ConstantBuilder {
+	/// Create a case sensitive constant with name and value
+	pub fn new<T: ConstantValue>(name: *const c_char, value: T) -> Self {
+		ConstantBuilder {
+			namespace: None,
+			name,
+			value: value.into_value(),
+			flags: CONST_CS,
+		}
+	}
+
+	/// Put the constant in a namespace, like `MyExtension\MODE_FAST`
+	pub fn with_namespace(mut self, namespace: *const c_char) -> Self {
+		self.namespace = Some(namespace);
+		self
+	}
+
+	/// Set the flags of the constant. `CONST_PERSISTENT` is always added.
+	pub fn with_flags(mut self, flags: c_int) -> Self {
+		self.flags = flags;
+		self
+	}
+
+	/// Build the constant
+	pub fn build(self) -> Constant {
+		let name = c_name_to_string(self.name);
+		let name = match self.namespace {
+			Some(namespace) => format!("{}\\{}", c_name_to_string(namespace).trim_end_matches('\\'), name),
+			None => name,
+		};
+		Constant {
+			name,
+			value: self.value,
+			flags: self.flags | CONST_PERSISTENT,
+		}
+	}
+}
+
+fn c_name_to_string(name: *const c_char) -> String {
+	unsafe { CStr::from_ptr(name) }.to_str().expect("Constant names must be valid UTF-8").to_string()
+}
+
+impl Constant {
+	pub(crate) fn register(self, module_number: c_int) {
+		let name = self.name.as_ptr() as *const c_char;
+		let name_len = self.name.len();
+		unsafe {
+			match self.value {
+				Value::Null => zend_register_null_constant(name, name_len, self.flags, module_number),
+				Value::Bool(value) => zend_register_bool_constant(name, name_len, value as u8, self.flags, module_number),
+				Value::Integer(value) => zend_register_long_constant(name, name_len, value, self.flags, module_number),
+				Value::Float(value) => zend_register_double_constant(name, name_len, value, self.flags, module_number),
+				Value::String(value) => zend_register_stringl_constant(name, name_len, value.as_ptr() as *const c_char, value.len(), self.flags, module_number),
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn values_are_copied_for_registration() {
+		assert_eq!(().into_value(), Value::Null);
+		assert_eq!(true.into_value(), Value::Bool(true));
+		assert_eq!(7.into_value(), Value::Integer(7));
+		assert_eq!(u32::MAX.into_value(), Value::Integer(u32::MAX as i64));
+		assert_eq!(1.5.into_value(), Value::Float(1.5));
+		assert_eq!("fast".into_value(), Value::String(b"fast".to_vec()));
+		assert_eq!(b"\xff"[..].into_value(), Value::String(vec![0xff]));
+		assert_eq!(None::<i64>.into_value(), Value::Null);
+		assert_eq!(Some("safe".to_string()).into_value(), Value::String(b"safe".to_vec()));
+	}
+
+	#[test]
+	fn namespace_prefixes_the_name() {
+		let constant = ConstantBuilder::new(c_str!("MODE_SAFE"), 2).with_namespace(c_str!("Hello\\")).build();
+		assert_eq!(constant.name, "Hello\\MODE_SAFE");
+		assert_eq!(constant.flags, CONST_CS | CONST_PERSISTENT);
+	}
+}
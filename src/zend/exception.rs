@@ -0,0 +1,251 @@
+use std::error::Error;
+use std::ffi::CString;
+use std::{mem, ptr};
+use libc::c_char;
+
+use super::class::{ClassEntry, ClassBuilder, Class, find_class};
+use super::internal_php_methods::*;
+use super::module::ACC_PROTECTED;
+use super::types::*;
+
+/// Throws a PHP exception of the given class. If the class is null, a `\Exception` is thrown.
+/// The exception is only raised by PHP after your handler returns, so you should return right after.
+///
+/// # Safety
+///
+/// `class` must be null or a class entry of the engine, like the ones returned by `find_class`.
+///
+/// ```no_run
+/// use solder::zend::{throw_exception, find_class};
+/// let class = find_class("InvalidArgumentException").unwrap();
+/// unsafe { throw_exception(class, "Name can't be empty", 0) };
+/// ```
+pub unsafe fn throw_exception(class: *mut ClassEntry, message: &str, code: i64) {
+    throw_exception_object(class, message, code);
+}
+
+fn throw_exception_object(class: *mut ClassEntry, message: &str, code: i64) -> *mut ZendObject {
+    let message = match message.find('\0') {
+        Some(end) => &message[..end],
+        None => message,
+    };
+    let c_message = CString::new(message).unwrap();
+    unsafe {
+        zend_throw_exception(class, c_message.as_ptr(), code)
+    }
+}
+
+/// Returns the value inside an `Ok` back to PHP or throws the error inside an `Err`.
+/// The error must implement `IntoPhpException`, so handlers can use `?` in a function returning
+/// `Result` and let this macro turn the error into an exception.
+///
+/// ```no_run
+/// # use solder::*;
+/// use solder::zend::{ExecuteData, Zval, FromPhpZval, PhpTypeConversionError};
+///
+/// fn hello(name_zval: Zval) -> Result<String, PhpTypeConversionError> {
+///     let name = String::try_from(name_zval)?;
+///     Ok(format!("Hello {}", name))
+/// }
+///
+/// pub fn hello_world(data: &ExecuteData, retval: &mut Zval) {
+///     let mut name_zval = Zval::new_as_null();
+///     php_parse_parameters!(data, &mut name_zval);
+///     php_return_result!(retval, hello(name_zval));
+/// }
+/// ```
+#[macro_export]
+macro_rules! php_return_result {
+    ($retval:expr, $result:expr) => {
+        match $result {
+            Ok(value) => {
+                $crate::php_return!($retval, value);
+            },
+            Err(error) => {
+                $crate::zend::IntoPhpException::into_php_exception(error).throw();
+                return;
+            },
+        }
+    };
+}
+
+/// Declares an exception class. By default it extends `\Exception`.
+///
+/// ```no_run
+/// # use solder::*;
+/// # use solder::zend::*;
+/// # fn get_module() -> *mut Module {
+/// let parse_error = ExceptionBuilder::new(c_str!("MyExt\\ParseError"))
+///     .with_parent(c_str!("RuntimeException"))
+///     .with_property(c_str!("line"), 0)
+///     .build();
+/// ModuleBuilder::new(c_str!("my_ext"), c_str!("0.1.0-dev"))
+///     .with_exception(parse_error)
+///     .build()
+///     .into_raw()
+/// # }
+/// ```
+pub struct ExceptionBuilder {
+    class: ClassBuilder,
+    has_parent: bool,
+}
+
+impl ExceptionBuilder {
+    /// Create an exception class with name
+    pub fn new(name: *const c_char) -> Self {
+        ExceptionBuilder {
+            class: ClassBuilder::new(name),
+            has_parent: false,
+        }
+    }
+
+    /// Extend another exception class, like `RuntimeException` or an exception declared before
+    pub fn with_parent(mut self, parent: *const c_char) -> Self {
+        self.class = self.class.with_parent(parent);
+        self.has_parent = true;
+        self
+    }
+
+    /// Declare a protected property with a default value
+    pub fn with_property<T>(mut self, name: *const c_char, default: T) -> Self
+        where Zval: From<T>
+    {
+        self.class = self.class.with_property(name, default, ACC_PROTECTED);
+        self
+    }
+
+    /// Build the exception class
+    pub fn build(self) -> Class {
+        if self.has_parent {
+            return self.class.build();
+        }
+        self.class.with_parent(c_str!("Exception")).build()
+    }
+}
+
+/// An exception to be thrown to PHP, identified by its class name. If the class can't be found
+/// by `find_class` a `\Exception` is thrown instead.
+///
+/// ```no_run
+/// # use solder::zend::PhpException;
+/// PhpException::new("MyExt\\ParseError", "Unexpected token")
+///     .with_code(10)
+///     .with_property("line", 42)
+///     .throw();
+/// ```
+pub struct PhpException {
+    class: String,
+    message: String,
+    code: i64,
+    properties: Vec<(String, Zval)>,
+    previous: Option<Box<PhpException>>,
+}
+
+impl PhpException {
+    /// Create an exception of class with message
+    pub fn new(class: &str, message: &str) -> Self {
+        PhpException {
+            class: class.to_string(),
+            message: message.to_string(),
+            code: 0,
+            properties: Vec::new(),
+            previous: None,
+        }
+    }
+
+    /// Set the exception code
+    pub fn with_code(mut self, code: i64) -> Self {
+        self.code = code;
+        self
+    }
+
+    /// Set a property of the exception object, like the ones declared with `ExceptionBuilder`
+    pub fn with_property<T>(mut self, name: &str, value: T) -> Self
+        where Zval: From<T>
+    {
+        self.properties.push((name.to_string(), Zval::from(value)));
+        self
+    }
+
+    /// Set the exception that caused this one, available in PHP through `getPrevious()`
+    pub fn with_previous(mut self, previous: PhpException) -> Self {
+        self.previous = Some(Box::new(previous));
+        self
+    }
+
+    /// Throws the exception. As with `throw_exception`, you should return from your handler right after.
+    pub fn throw(self) {
+        // PHP chains an exception thrown while another one is pending as its previous
+        if let Some(previous) = self.previous {
+            previous.throw();
+        }
+        let class = find_class(&self.class).unwrap_or(ptr::null_mut());
+        let object = throw_exception_object(class, &self.message, self.code);
+        if object.is_null() {
+            return;
+        }
+        // The engine owns the exception, so the zval must not release it
+        let mut object_zval = mem::ManuallyDrop::new(Zval::from(object));
+        for (name, mut value) in self.properties {
+            let c_name = CString::new(name.as_str()).unwrap();
+            unsafe {
+                zend_update_property((*object).ce, &mut *object_zval, c_name.as_ptr(), name.len(), &mut value);
+            }
+        }
+    }
+}
+
+/// Converts an error into the exception thrown to PHP. Implement it for your error types to choose
+/// the class, message, code and previous exception of each one.
+///
+/// ```no_run
+/// # use solder::zend::{IntoPhpException, PhpException, PhpTypeConversionError};
+/// enum ConfigError {
+///     Missing(String),
+///     Invalid(String, PhpTypeConversionError),
+/// }
+///
+/// impl IntoPhpException for ConfigError {
+///     fn into_php_exception(self) -> PhpException {
+///         match self {
+///             ConfigError::Missing(key) => PhpException::new("OutOfBoundsException", &format!("Missing {}", key)).with_code(1),
+///             ConfigError::Invalid(key, error) => PhpException::new("UnexpectedValueException", &format!("Invalid {}", key))
+///                 .with_code(2)
+///                 .with_previous(error.into_php_exception()),
+///         }
+///     }
+/// }
+/// ```
+pub trait IntoPhpException {
+    fn into_php_exception(self) -> PhpException;
+}
+
+impl IntoPhpException for PhpException {
+    fn into_php_exception(self) -> PhpException {
+        self
+    }
+}
+
+impl IntoPhpException for PhpTypeConversionError {
+    fn into_php_exception(self) -> PhpException {
+        PhpException::new("TypeError", &self.to_string())
+    }
+}
+
+impl IntoPhpException for String {
+    fn into_php_exception(self) -> PhpException {
+        PhpException::new("Exception", &self)
+    }
+}
+
+impl IntoPhpException for &str {
+    fn into_php_exception(self) -> PhpException {
+        PhpException::new("Exception", self)
+    }
+}
+
+impl IntoPhpException for Box<dyn Error> {
+    fn into_php_exception(self) -> PhpException {
+        PhpException::new("Exception", &self.to_string())
+    }
+}
@@ -0,0 +1,197 @@
+use std::alloc::Layout;
+#[cfg(not(feature = "zts"))]
+use std::alloc::{alloc, handle_alloc_error};
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::ptr;
+use std::sync::Mutex;
+use libc::*;
+
+#[cfg(feature = "zts")]
+use super::internal_php_methods::*;
+use super::panic::{catch_panic, raise_pending_fatal_error};
+
+/// Type of the globals given to `ModuleBuilder::with_globals` and where the engine keeps them.
+/// Without ZTS it is the address of the globals, with ZTS the address of their resource id.
+///
+/// The globals are stored as a `RefCell<Option<T>>`, which stays `None` if `T::default()` panics.
+struct GlobalsSlot {
+	type_id: TypeId,
+	location: usize,
+}
+
+static GLOBALS: Mutex<Option<GlobalsSlot>> = Mutex::new(None);
+
+/// Size, storage and constructors to put in the module entry
+pub(crate) struct GlobalsEntry {
+	pub(crate) size: size_t,
+	pub(crate) location: *const c_void,
+	pub(crate) ctor: extern "C" fn(globals: *mut c_void),
+	pub(crate) dtor: extern "C" fn(globals: *mut c_void),
+}
+
+pub(crate) fn globals_entry<T: Default + 'static>() -> GlobalsEntry {
+	GlobalsEntry {
+		size: std::mem::size_of::<RefCell<Option<T>>>(),
+		location: reserve_globals::<T>(),
+		ctor: globals_ctor::<T>,
+		dtor: globals_dtor::<T>,
+	}
+}
+
+/// Allocates where the engine keeps the globals, and remembers it for `module_globals`
+fn reserve_globals<T: 'static>() -> *const c_void {
+	let mut slot = GLOBALS.lock().unwrap();
+	assert!(slot.is_none(), "The module already has globals");
+	let location = allocate_location(Layout::new::<RefCell<Option<T>>>());
+	*slot = Some(GlobalsSlot { type_id: TypeId::of::<T>(), location: location as usize });
+	location
+}
+
+#[cfg(not(feature = "zts"))]
+fn allocate_location(layout: Layout) -> *const c_void {
+	let location = unsafe { alloc(layout) };
+	if location.is_null() {
+		handle_alloc_error(layout);
+	}
+	location as *const c_void
+}
+
+#[cfg(feature = "zts")]
+fn allocate_location(_layout: Layout) -> *const c_void {
+	// The engine writes the resource id here and allocates the globals for each thread
+	Box::into_raw(Box::new(0 as c_int)) as *const c_void
+}
+
+extern "C" fn globals_ctor<T: Default>(globals: *mut c_void) {
+	let value = catch_panic(T::default);
+	unsafe { write_globals(globals, value) };
+	raise_pending_fatal_error();
+}
+
+extern "C" fn globals_dtor<T>(globals: *mut c_void) {
+	catch_panic(|| unsafe { drop_globals::<T>(globals) });
+	raise_pending_fatal_error();
+}
+
+/// Stores the globals, `None` when they could not be created
+unsafe fn write_globals<T>(globals: *mut c_void, value: Option<T>) {
+	ptr::write(globals as *mut RefCell<Option<T>>, RefCell::new(value));
+}
+
+unsafe fn drop_globals<T>(globals: *mut c_void) {
+	ptr::drop_in_place(globals as *mut RefCell<Option<T>>);
+}
+
+#[cfg(not(feature = "zts"))]
+fn globals_pointer(location: usize) -> *mut c_void {
+	location as *mut c_void
+}
+
+#[cfg(feature = "zts")]
+fn globals_pointer(location: usize) -> *mut c_void {
+	unsafe { ts_resource_ex(*(location as *const c_int), ptr::null_mut()) }
+}
+
+/// Runs `f` with the globals declared with `ModuleBuilder::with_globals::<T>()`.
+/// Each process has its own globals, or each thread with a thread safe (ZTS) PHP.
+///
+/// It panics if `T` is not the type of the globals, if `T::default()` panicked when creating them,
+/// or if they are already borrowed by a caller of this function.
+pub fn module_globals<T: 'static, R>(f: impl FnOnce(&mut T) -> R) -> R {
+	let location = GLOBALS.lock().unwrap()
+		.as_ref()
+		.filter(|slot| slot.type_id == TypeId::of::<T>())
+		.map(|slot| slot.location)
+		.expect("The module has no globals of this type");
+	let globals = unsafe { &*(globals_pointer(location) as *const RefCell<Option<T>>) };
+	let mut globals = globals.try_borrow_mut().expect("The module globals are already borrowed");
+	f(globals.as_mut().expect("The module globals could not be created"))
+}
+
+
+#[cfg(all(test, not(feature = "zts")))]
+mod tests {
+	use super::*;
+	use std::panic;
+	use std::sync::{MutexGuard, PoisonError};
+
+	static TEST_LOCK: Mutex<()> = Mutex::new(());
+	static EVENTS: Mutex<Vec<&str>> = Mutex::new(Vec::new());
+
+	#[derive(Debug)]
+	struct Counter {
+		value: i64,
+	}
+
+	impl Default for Counter {
+		fn default() -> Self {
+			EVENTS.lock().unwrap().push("created");
+			Counter { value: 1 }
+		}
+	}
+
+	impl Drop for Counter {
+		fn drop(&mut self) {
+			EVENTS.lock().unwrap().push("dropped");
+		}
+	}
+
+	/// The globals are shared by the process, so each test starts without any
+	fn without_globals() -> MutexGuard<'static, ()> {
+		let guard = TEST_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+		GLOBALS.clear_poison();
+		*GLOBALS.lock().unwrap() = None;
+		EVENTS.lock().unwrap().clear();
+		guard
+	}
+
+	#[test]
+	fn globals_are_created_before_use_and_dropped_after() {
+		let _guard = without_globals();
+		let globals = reserve_globals::<Counter>() as *mut c_void;
+		unsafe { write_globals(globals, Some(Counter::default())) };
+		module_globals(|counter: &mut Counter| counter.value += 1);
+		assert_eq!(module_globals(|counter: &mut Counter| counter.value), 2);
+		assert_eq!(*EVENTS.lock().unwrap(), ["created"]);
+		unsafe { drop_globals::<Counter>(globals) };
+		assert_eq!(*EVENTS.lock().unwrap(), ["created", "dropped"]);
+	}
+
+	#[test]
+	fn globals_that_could_not_be_created_panic_when_used() {
+		let _guard = without_globals();
+		let globals = reserve_globals::<Counter>() as *mut c_void;
+		// What the constructor stores when `Counter::default()` panics
+		unsafe { write_globals::<Counter>(globals, None) };
+		let error = panic::catch_unwind(|| module_globals(|counter: &mut Counter| counter.value)).unwrap_err();
+		assert_eq!(error.downcast_ref::<String>().map(String::as_str), Some("The module globals could not be created"));
+		unsafe { drop_globals::<Counter>(globals) };
+		assert!(EVENTS.lock().unwrap().is_empty());
+	}
+
+	#[test]
+	#[should_panic(expected = "The module has no globals of this type")]
+	fn globals_of_another_type_panic() {
+		let _guard = without_globals();
+		reserve_globals::<Counter>();
+		module_globals(|value: &mut i64| *value);
+	}
+
+	#[test]
+	#[should_panic(expected = "The module globals are already borrowed")]
+	fn nested_access_panics() {
+		let _guard = without_globals();
+		let globals = reserve_globals::<i64>() as *mut c_void;
+		unsafe { write_globals(globals, Some(0_i64)) };
+		module_globals(|_: &mut i64| module_globals(|value: &mut i64| *value));
+	}
+
+	#[test]
+	#[should_panic(expected = "The module already has globals")]
+	fn a_module_has_one_set_of_globals() {
+		let _guard = without_globals();
+		reserve_globals::<Counter>();
+		reserve_globals::<i64>();
+	}
+}
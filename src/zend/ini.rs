@@ -0,0 +1,265 @@
+use std::ffi::CStr;
+use std::{ptr, slice, str};
+use libc::*;
+
+use super::internal_php_methods::*;
+use super::module::{SUCCESS, FAILURE};
+use super::panic::{catch_panic, raise_pending_fatal_error};
+use super::types::*;
+
+/// The entry can be changed with `ini_set`
+pub const INI_USER: c_int = 1;
+/// The entry can be changed in `.htaccess` and `.user.ini` files
+pub const INI_PERDIR: c_int = 2;
+/// The entry can be changed in `php.ini` or `httpd.conf`
+pub const INI_SYSTEM: c_int = 4;
+/// The entry can be changed anywhere
+pub const INI_ALL: c_int = INI_USER | INI_PERDIR | INI_SYSTEM;
+
+/// Type of the value of an INI entry. Values that can't be read as it are rejected.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum IniType {
+	String,
+	/// An integer, optionally followed by `K`, `M` or `G` like `128M`, as PHP reads `memory_limit`
+	Integer,
+	Float,
+	/// `1`, `0`, `On`, `Off`, `Yes`, `No`, `True`, `False`, `None` or empty
+	Bool,
+}
+
+/// Entry of the module INI settings (`zend_ini_entry_def`)
+#[repr(C)]
+pub struct IniEntry {
+	name: *const c_char,
+	on_modify: Option<OnModifyFunc>,
+	mh_arg1: *mut c_void,
+	mh_arg2: *mut c_void,
+	mh_arg3: *mut c_void,
+	value: *const c_char,
+	displayer: *const c_void,
+	modifiable: c_int,
+	name_length: u32,
+	value_length: u32,
+}
+
+/// A registered INI entry (`zend_ini_entry`), only passed around
+#[repr(C)]
+struct ZendIniEntry {
+	_private: [u8; 0],
+}
+
+type OnModifyFunc = extern "C" fn (entry: *mut ZendIniEntry, new_value: *mut ZendString, mh_arg1: *mut c_void, mh_arg2: *mut c_void, mh_arg3: *mut c_void, stage: c_int) -> c_int;
+
+type OnModifyCallback = Box<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// What the on modify handler checks, kept in the first argument of the entry
+struct IniValidation {
+	ini_type: IniType,
+	on_modify: Option<OnModifyCallback>,
+}
+
+impl IniEntry {
+	pub(crate) fn end() -> IniEntry {
+		IniEntry {
+			name: ptr::null(),
+			on_modify: None,
+			mh_arg1: ptr::null_mut(),
+			mh_arg2: ptr::null_mut(),
+			mh_arg3: ptr::null_mut(),
+			value: ptr::null(),
+			displayer: ptr::null(),
+			modifiable: 0,
+			name_length: 0,
+			value_length: 0,
+		}
+	}
+}
+
+unsafe impl Send for IniEntry {}
+
+pub struct IniEntryBuilder {
+	name: *const c_char,
+	default: *const c_char,
+	modifiable: c_int,
+	validation: IniValidation,
+}
+
+impl IniEntryBuilder {
+	/// Create an entry with name and default value, of type `String` and changeable anywhere
+	pub fn new(name: *const c_char, default: *const c_char) -> Self {
+		IniEntryBuilder {
+			name,
+			default,
+			modifiable: INI_ALL,
+			validation: IniValidation { ini_type: IniType::String, on_modify: None },
+		}
+	}
+
+	/// Set where the entry can be changed, e.g. `INI_SYSTEM` or `INI_SYSTEM | INI_PERDIR`
+	pub fn with_scope(mut self, modifiable: c_int) -> Self {
+		self.modifiable = modifiable;
+		self
+	}
+
+	/// Set the type of the value
+	pub fn with_type(mut self, ini_type: IniType) -> Self {
+		self.validation.ini_type = ini_type;
+		self
+	}
+
+	/// Set a function that validates every new value, from `php.ini` or `ini_set`.
+	/// The value is rejected if it returns false or panics. Panics are reported like the ones of
+	/// handlers, so an `ini_set` call throws a `\Error`.
+	pub fn with_on_modify<F>(mut self, on_modify: F) -> Self
+		where F: Fn(&str) -> bool + Send + Sync + 'static
+	{
+		self.validation.on_modify = Some(Box::new(on_modify));
+		self
+	}
+
+	/// Build the entry
+	pub fn build(self) -> IniEntry {
+		unsafe {
+			IniEntry {
+				name: self.name,
+				on_modify: Some(on_modify),
+				mh_arg1: Box::into_raw(Box::new(self.validation)) as *mut c_void,
+				mh_arg2: ptr::null_mut(),
+				mh_arg3: ptr::null_mut(),
+				value: self.default,
+				displayer: ptr::null(),
+				modifiable: self.modifiable,
+				name_length: strlen(self.name) as u32,
+				value_length: strlen(self.default) as u32,
+			}
+		}
+	}
+}
+
+extern "C" fn on_modify(_entry: *mut ZendIniEntry, new_value: *mut ZendString, mh_arg1: *mut c_void, _mh_arg2: *mut c_void, _mh_arg3: *mut c_void, _stage: c_int) -> c_int {
+	let validation = unsafe { &*(mh_arg1 as *const IniValidation) };
+	let value = if new_value.is_null() {
+		Ok("")
+	} else {
+		unsafe { str::from_utf8(slice::from_raw_parts((*new_value).value.as_ptr(), (*new_value).len)) }
+	};
+	let accepted = match value {
+		Ok(value) => validation.ini_type.accepts(value) && match &validation.on_modify {
+			Some(on_modify) => catch_panic(|| on_modify(value)).unwrap_or(false),
+			None => true,
+		},
+		Err(_) => false,
+	};
+	raise_pending_fatal_error();
+	if accepted { SUCCESS } else { FAILURE }
+}
+
+impl IniType {
+	/// Checks that a new value can be read as this type
+	fn accepts(self, value: &str) -> bool {
+		match self {
+			IniType::String => true,
+			IniType::Integer => parse_integer(value).is_some(),
+			IniType::Float => value.trim().parse::<f64>().is_ok(),
+			IniType::Bool => parse_bool(value).is_some(),
+		}
+	}
+}
+
+/// Reads an integer with an optional `K`, `M` or `G` suffix, like `zend_atol`
+fn parse_integer(value: &str) -> Option<i64> {
+	let value = value.trim();
+	let (number, multiplier) = match value.chars().last()? {
+		'k' | 'K' => (&value[..value.len() - 1], 1 << 10),
+		'm' | 'M' => (&value[..value.len() - 1], 1 << 20),
+		'g' | 'G' => (&value[..value.len() - 1], 1 << 30),
+		_ => (value, 1),
+	};
+	number.trim_end().parse::<i64>().ok()?.checked_mul(multiplier)
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+	let value = value.trim();
+	match value.to_ascii_lowercase().as_str() {
+		"on" | "yes" | "true" => Some(true),
+		"off" | "no" | "false" | "none" | "" => Some(false),
+		_ => value.parse::<i64>().ok().map(|value| value != 0),
+	}
+}
+
+/// Registers the entries of the module, when it starts up
+pub(crate) fn register_ini_entries(mut entries: Vec<IniEntry>, module_number: c_int) {
+	entries.push(IniEntry::end());
+	let entries: &'static [IniEntry] = Box::leak(entries.into_boxed_slice());
+	unsafe {
+		zend_register_ini_entries(entries.as_ptr(), module_number);
+	}
+}
+
+/// Returns the current value of an INI entry, or `None` if there is no entry with this name
+pub fn ini_string(name: &str) -> Option<String> {
+	let mut exists = 0;
+	unsafe {
+		let value = zend_ini_string_ex(name.as_ptr() as *const c_char, name.len(), 0, &mut exists);
+		if exists == 0 {
+			return None;
+		}
+		if value.is_null() {
+			return Some(String::new());
+		}
+		Some(CStr::from_ptr(value).to_string_lossy().into_owned())
+	}
+}
+
+/// Returns the current value of an INI entry as an integer. A `K`, `M` or `G` suffix multiplies
+/// it like for `memory_limit`, and other values are read like PHP does.
+pub fn ini_integer(name: &str) -> Option<i64> {
+	let value = ini_string(name)?;
+	parse_integer(&value).or_else(|| unsafe { Some(zend_ini_long(name.as_ptr() as *const c_char, name.len(), 0)) })
+}
+
+/// Returns the current value of an INI entry as a float, read like PHP does
+pub fn ini_float(name: &str) -> Option<f64> {
+	ini_string(name)?;
+	unsafe { Some(zend_ini_double(name.as_ptr() as *const c_char, name.len(), 0)) }
+}
+
+/// Returns the current value of an INI entry as a bool. `On`, `Yes` and `True` are true.
+pub fn ini_bool(name: &str) -> Option<bool> {
+	ini_string(name).map(|value| parse_bool(&value).unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn values_must_match_the_entry_type() {
+		assert!(IniType::Integer.accepts(" 42 "));
+		assert!(!IniType::Integer.accepts("4.2"));
+		assert!(IniType::Float.accepts("4.2"));
+		assert!(IniType::Bool.accepts("Yes"));
+		assert!(!IniType::Bool.accepts("maybe"));
+		assert!(IniType::String.accepts("anything"));
+	}
+
+	#[test]
+	fn integers_accept_quantities() {
+		assert_eq!(parse_integer("128M"), Some(128 << 20));
+		assert_eq!(parse_integer(" 2k "), Some(2048));
+		assert_eq!(parse_integer("-1"), Some(-1));
+		assert_eq!(parse_integer("1G"), Some(1 << 30));
+		assert_eq!(parse_integer("M"), None);
+		assert_eq!(parse_integer("12T"), None);
+		assert_eq!(parse_integer("9223372036854775807K"), None);
+	}
+
+	#[test]
+	fn bools_follow_php() {
+		assert_eq!(parse_bool("On"), Some(true));
+		assert_eq!(parse_bool(" none "), Some(false));
+		assert_eq!(parse_bool("2"), Some(true));
+		assert_eq!(parse_bool("0"), Some(false));
+		assert_eq!(parse_bool("enabled"), None);
+	}
+}
@@ -1,38 +1,119 @@
 use super::types::*;
+use super::class::{ClassEntry, ObjectHandlers};
+use super::ini::IniEntry;
 use libc::*;
+use std::{mem, ptr};
 
 extern "C" {
-    pub fn zend_parse_parameters(num_args: i32, format: *const c_char, ...) -> i32;
     pub fn array_set_zval_key(ht: *mut ZendArray, key: *mut Zval, value: *mut Zval) -> i32;
-    pub fn php_printf(format: *const c_char , ...) -> size_t;
+    pub fn php_output_write(string: *const c_char, length: size_t) -> size_t;
+    pub fn zend_error(error_type: c_int, format: *const c_char, ...);
+    pub fn zend_is_executing() -> u8;
     pub fn _call_user_function_ex(object: *mut Zval, function_name: *mut Zval, retval_ptr: *mut Zval, param_count: u32, params: *mut Zval, no_separation: i32) -> i32;
     pub fn zend_get_callable_name(callable: *mut Zval) -> *mut ZendString;
+    pub fn zend_is_callable(callable: *mut Zval, check_flags: u32, callable_name: *mut *mut ZendString) -> u8;
+    pub fn zend_parse_arg_long_slow(arg: *mut Zval, dest: *mut i64) -> c_int;
+    pub fn zend_parse_arg_double_slow(arg: *mut Zval, dest: *mut f64) -> c_int;
+    pub fn zend_parse_arg_bool_slow(arg: *mut Zval, dest: *mut u8) -> c_int;
+    pub fn zend_parse_arg_str_slow(arg: *mut Zval, dest: *mut *mut ZendString) -> c_int;
     pub fn _efree(ptr: *mut c_void);
-    pub fn free(ptr: *mut c_void);
+    pub fn _emalloc(size: size_t) -> *mut c_void;
+    pub fn _ecalloc(nmemb: size_t, size: size_t) -> *mut c_void;
+    pub fn zend_throw_exception(exception_class: *mut ClassEntry, message: *const c_char, code: i64) -> *mut ZendObject;
+    pub fn zend_register_internal_class_ex(class_entry: *mut ClassEntry, parent: *mut ClassEntry) -> *mut ClassEntry;
+    pub fn zend_declare_property_null(class_entry: *mut ClassEntry, name: *const c_char, name_length: size_t, flags: c_int) -> c_int;
+    pub fn zend_declare_property_bool(class_entry: *mut ClassEntry, name: *const c_char, name_length: size_t, value: i64, flags: c_int) -> c_int;
+    pub fn zend_declare_property_long(class_entry: *mut ClassEntry, name: *const c_char, name_length: size_t, value: i64, flags: c_int) -> c_int;
+    pub fn zend_declare_property_double(class_entry: *mut ClassEntry, name: *const c_char, name_length: size_t, value: f64, flags: c_int) -> c_int;
+    pub fn zend_declare_property_stringl(class_entry: *mut ClassEntry, name: *const c_char, name_length: size_t, value: *const c_char, value_length: size_t, flags: c_int) -> c_int;
+    pub fn zend_update_property(scope: *mut ClassEntry, object: *mut Zval, name: *const c_char, name_length: size_t, value: *mut Zval);
+    pub fn zend_object_std_init(object: *mut ZendObject, class_entry: *mut ClassEntry);
+    pub fn zend_object_std_dtor(object: *mut ZendObject);
+    pub fn object_properties_init(object: *mut ZendObject, class_entry: *mut ClassEntry);
+    pub fn zend_objects_new(class_entry: *mut ClassEntry) -> *mut ZendObject;
+    pub fn zend_objects_clone_members(new_object: *mut ZendObject, old_object: *mut ZendObject);
+    pub fn zend_hash_index_find(ht: *const ZendArray, h: u64) -> *mut Zval;
+    pub fn zend_hash_str_find(ht: *const ZendArray, key: *const c_char, len: size_t) -> *mut Zval;
+    pub fn zend_hash_index_del(ht: *mut ZendArray, h: u64) -> c_int;
+    pub fn zend_hash_str_del(ht: *mut ZendArray, key: *const c_char, len: size_t) -> c_int;
+    pub fn zend_array_dup(source: *mut ZendArray) -> *mut ZendArray;
+    pub static std_object_handlers: ObjectHandlers;
+    pub fn zend_register_ini_entries(ini_entry: *const IniEntry, module_number: c_int) -> c_int;
+    pub fn zend_unregister_ini_entries(module_number: c_int);
+    pub fn display_ini_entries(module: *const c_void);
+    pub fn zend_register_null_constant(name: *const c_char, name_len: size_t, flags: c_int, module_number: c_int);
+    pub fn zend_register_bool_constant(name: *const c_char, name_len: size_t, bval: u8, flags: c_int, module_number: c_int);
+    pub fn zend_register_long_constant(name: *const c_char, name_len: size_t, lval: i64, flags: c_int, module_number: c_int);
+    pub fn zend_register_double_constant(name: *const c_char, name_len: size_t, dval: f64, flags: c_int, module_number: c_int);
+    pub fn zend_register_stringl_constant(name: *const c_char, name_len: size_t, strval: *const c_char, strlen: size_t, flags: c_int, module_number: c_int);
+    pub fn zend_ini_string_ex(name: *const c_char, name_length: size_t, orig: c_int, exists: *mut u8) -> *mut c_char;
+    pub fn zend_ini_long(name: *const c_char, name_length: size_t, orig: c_int) -> i64;
+    pub fn zend_ini_double(name: *const c_char, name_length: size_t, orig: c_int) -> f64;
+    static zend_new_interned_string: extern "C" fn(string: *mut ZendString) -> *mut ZendString;
 }
 
-#[cfg(feature = "php72")]
+#[allow(non_upper_case_globals)]
 extern "C" {
-    fn zend_strpprintf(max_len: size_t, format: * const c_char) -> * mut ZendString;
+    pub static zend_ce_exception: *mut ClassEntry;
+    pub static zend_ce_error_exception: *mut ClassEntry;
+    pub static zend_ce_error: *mut ClassEntry;
+    pub static zend_ce_type_error: *mut ClassEntry;
+    pub static zend_ce_argument_count_error: *mut ClassEntry;
+    pub static zend_ce_arithmetic_error: *mut ClassEntry;
+    pub static zend_ce_division_by_zero_error: *mut ClassEntry;
+    pub static spl_ce_LogicException: *mut ClassEntry;
+    pub static spl_ce_BadFunctionCallException: *mut ClassEntry;
+    pub static spl_ce_BadMethodCallException: *mut ClassEntry;
+    pub static spl_ce_DomainException: *mut ClassEntry;
+    pub static spl_ce_InvalidArgumentException: *mut ClassEntry;
+    pub static spl_ce_LengthException: *mut ClassEntry;
+    pub static spl_ce_OutOfRangeException: *mut ClassEntry;
+    pub static spl_ce_RuntimeException: *mut ClassEntry;
+    pub static spl_ce_OutOfBoundsException: *mut ClassEntry;
+    pub static spl_ce_OverflowException: *mut ClassEntry;
+    pub static spl_ce_RangeException: *mut ClassEntry;
+    pub static spl_ce_UnderflowException: *mut ClassEntry;
+    pub static spl_ce_UnexpectedValueException: *mut ClassEntry;
 }
 
-#[cfg(feature = "php72")]
-pub fn create_zend_string(size: size_t, string: *const c_char) -> * mut ZendString {
+/// Creates a string with a copy of the bytes, like `zend_string_init`.
+/// Persistent strings are allocated with malloc and outlive the requests.
+pub fn zend_string_init(bytes: &[u8], persistent: bool) -> *mut ZendString {
     unsafe {
-        zend_strpprintf(size, string)
+        let size = mem::size_of::<ZendString>() + bytes.len() + 1;
+        let zend_string = if persistent { malloc(size) } else { _emalloc(size) } as *mut ZendString;
+        let flags = if persistent { IS_STR_PERSISTENT } else { 0 };
+        (*zend_string).gc.ref_count = 1;
+        (*zend_string).gc.type_info = InternalPhpTypes::STRING as u32 | (flags << GC_FLAGS_SHIFT);
+        (*zend_string).hash = 0;
+        (*zend_string).len = bytes.len();
+        let value = (*zend_string).value.as_mut_ptr();
+        ptr::copy_nonoverlapping(bytes.as_ptr(), value, bytes.len());
+        *value.add(bytes.len()) = 0;
+        zend_string
     }
 }
 
-#[cfg(not(feature = "php72"))]
+/// Interns a string, releasing it if an equal one was already interned
+pub unsafe fn intern_zend_string(string: *mut ZendString) -> *mut ZendString {
+    zend_new_interned_string(string)
+}
+
+#[cfg(feature = "zts")]
 extern "C" {
-    fn strpprintf(max_len: size_t, format: *const c_char) -> *mut ZendString;
+    pub fn ts_resource_ex(id: c_int, th_id: *mut c_void) -> *mut c_void;
 }
 
-#[cfg(not(feature = "php72"))]
-pub fn create_zend_string(size: size_t, string: *const c_char) -> * mut ZendString {
-    unsafe {
-        strpprintf(size, string)
-    }
+#[cfg(feature = "php73")]
+extern "C" {
+    pub fn zend_wrong_parameters_count_error(min_num_args: c_int, max_num_args: c_int);
+    pub fn zend_wrong_parameter_type_error(num: c_int, expected_type: c_int, arg: *mut Zval);
+}
+
+#[cfg(not(feature = "php73"))]
+extern "C" {
+    pub fn zend_wrong_parameters_count_error(throw: u8, num_args: c_int, min_num_args: c_int, max_num_args: c_int);
+    pub fn zend_wrong_parameter_type_error(throw: u8, num: c_int, expected_type: c_int, arg: *mut Zval);
 }
 
 #[cfg(feature = "php73")]
@@ -43,7 +124,7 @@ extern "C" {
 #[cfg(feature = "php73")]
 pub fn create_zend_array(zval: &mut Zval) {
     unsafe {
-        zval.type_info.type_info = InternalPhpTypes::ARRAY as u32;
+        zval.type_info.type_info = IS_ARRAY_EX;
         zval.value.array = _zend_new_array_0();
     }
 }
@@ -58,4 +139,69 @@ pub fn create_zend_array(zval: &mut Zval) {
     unsafe {
         _array_init(zval, 0);
     }
-}
\ No newline at end of file
+}
+#[cfg(feature = "php73")]
+extern "C" {
+    fn zend_hash_index_update(ht: *mut ZendArray, h: u64, data: *mut Zval) -> *mut Zval;
+    fn zend_hash_str_update(ht: *mut ZendArray, key: *const c_char, len: size_t, data: *mut Zval) -> *mut Zval;
+    fn zend_hash_next_index_insert(ht: *mut ZendArray, data: *mut Zval) -> *mut Zval;
+}
+
+#[cfg(feature = "php73")]
+pub unsafe fn hash_index_update(ht: *mut ZendArray, h: u64, data: *mut Zval) -> *mut Zval {
+    zend_hash_index_update(ht, h, data)
+}
+
+#[cfg(feature = "php73")]
+pub unsafe fn hash_str_update(ht: *mut ZendArray, key: *const c_char, len: size_t, data: *mut Zval) -> *mut Zval {
+    zend_hash_str_update(ht, key, len, data)
+}
+
+#[cfg(feature = "php73")]
+pub unsafe fn hash_next_index_insert(ht: *mut ZendArray, data: *mut Zval) -> *mut Zval {
+    zend_hash_next_index_insert(ht, data)
+}
+
+#[cfg(not(feature = "php73"))]
+extern "C" {
+    fn _zend_hash_index_update(ht: *mut ZendArray, h: u64, data: *mut Zval) -> *mut Zval;
+    fn _zend_hash_str_update(ht: *mut ZendArray, key: *const c_char, len: size_t, data: *mut Zval) -> *mut Zval;
+    fn _zend_hash_next_index_insert(ht: *mut ZendArray, data: *mut Zval) -> *mut Zval;
+}
+
+#[cfg(not(feature = "php73"))]
+pub unsafe fn hash_index_update(ht: *mut ZendArray, h: u64, data: *mut Zval) -> *mut Zval {
+    _zend_hash_index_update(ht, h, data)
+}
+
+#[cfg(not(feature = "php73"))]
+pub unsafe fn hash_str_update(ht: *mut ZendArray, key: *const c_char, len: size_t, data: *mut Zval) -> *mut Zval {
+    _zend_hash_str_update(ht, key, len, data)
+}
+
+#[cfg(not(feature = "php73"))]
+pub unsafe fn hash_next_index_insert(ht: *mut ZendArray, data: *mut Zval) -> *mut Zval {
+    _zend_hash_next_index_insert(ht, data)
+}
+
+#[cfg(feature = "php73")]
+extern "C" {
+    fn zval_ptr_dtor(zval: *mut Zval);
+}
+
+/// Releases one reference to the value of a zval, destroying it or collecting its cycles as needed
+#[cfg(feature = "php73")]
+pub unsafe fn release_zval(zval: *mut Zval) {
+    zval_ptr_dtor(zval)
+}
+
+#[cfg(not(feature = "php73"))]
+extern "C" {
+    fn _zval_ptr_dtor(zval: *mut Zval);
+}
+
+/// Releases one reference to the value of a zval, destroying it or collecting its cycles as needed
+#[cfg(not(feature = "php73"))]
+pub unsafe fn release_zval(zval: *mut Zval) {
+    _zval_ptr_dtor(zval)
+}
@@ -1,15 +1,17 @@
 use super::internal_php_methods::*;
+use super::parameters::*;
 use super::types::*;
-use std::ffi::CString;
+use super::string::PhpString;
+use libc::c_char;
 
 /// As the name suggests, this method is acts like a PHP echo
-/// ```
+/// ```no_run
+/// # use solder::zend::php_echo;
 /// php_echo("hello world");
 /// ```
 pub fn php_echo(message: &str) {
-    let c_message = CString::new(message).unwrap();
     unsafe {
-        php_printf(c_message.as_bytes_with_nul().as_ptr() as *const i8);
+        php_output_write(message.as_ptr() as *const c_char, message.len());
     }
 }
 
@@ -18,18 +20,21 @@ pub fn php_echo(message: &str) {
 ///
 /// This method does not checks if the closure is an actually closure. If you pass a Zval that is
 /// not a closure PHP may throw an error.
-/// ```
+/// ```no_run
+/// # use solder::*;
+/// # use solder::zend::{ExecuteData, Zval};
 /// use solder::zend::execute_closure;
+/// # fn handler(data: &ExecuteData) {
 /// let mut closure = Zval::new_as_null();
-/// php_parse_parameters!(&mut closure);
-/// execute_closure(closure, &mut [Zval::from("Hello World")]);
+/// php_parse_parameters!(data, &mut closure);
+/// execute_closure(&mut closure, &mut [Zval::from("Hello World")]);
+/// # }
 /// ```
 pub fn execute_closure(callable: &mut Zval, params: &mut [Zval]) -> Zval {
     let mut returner = Zval::new_as_null();
     unsafe{
-        let mut callable_name = Zval::from(zend_get_callable_name(callable));
+        let mut callable_name = PhpString::from_raw(zend_get_callable_name(callable)).into_zval();
         _call_user_function_ex(callable, &mut callable_name, &mut returner, params.len() as u32, params.as_mut_ptr(), 0);
-        free_zend_string(callable_name.value.string);
     };
     returner
 }
@@ -37,11 +42,15 @@ pub fn execute_closure(callable: &mut Zval, params: &mut [Zval]) -> Zval {
 /// Macro to simplify executing a closure. The first parameter is the closure and the others are the
 /// parameters. It will return the closure return
 ///
-/// ```
+/// ```no_run
+/// # use solder::*;
+/// # use solder::zend::{ExecuteData, Zval, FromPhpZval};
 /// use solder::zend::execute_closure;
+/// # fn handler(data: &ExecuteData) {
 /// let mut closure = Zval::new_as_null();
-/// php_parse_parameters!(&mut closure);
-/// let message = String::try_from(execute_closure!(closure, "Hello ", "World", 5)).expect("Error");
+/// php_parse_parameters!(data, &mut closure);
+/// let message = String::try_from(execute_closure!(&mut closure, "Hello ", "World", 5)).expect("Error");
+/// # }
 /// ```
 #[macro_export]
 macro_rules! execute_closure{
@@ -53,139 +62,61 @@ macro_rules! execute_closure{
 	}
 }
 
-/// This macro parses all parameters passed to function. Currently, there is a limit of 5 parameters.
-/// If you try to get more parameters than what were passed to the function, PHP will emit a Warning
-/// and the excess zvals will be undefined.
+/// This macro parses all parameters passed to function as Zvals. The first parameter is the
+/// `ExecuteData` received by the handler. If the number of parameters passed from PHP is different
+/// from the number of zvals, PHP emits its error and the macro returns from the handler.
 ///
-/// ```
+/// ```no_run
+/// # use solder::*;
 /// use solder::zend::{ExecuteData, Zval, FromPhpZval};
-/// #[no_mangle]
-/// pub extern fn hello_world(_data: &ExecuteData, retval: &mut Zval) {
+/// pub fn hello_world(data: &ExecuteData, retval: &mut Zval) {
 ///     let mut name_zval = Zval::new_as_null();
-///     php_parse_parameters!(&mut name_zval);
-///     let name = String::try_from(name_zval).ok().unwrap();
-///     let hello = format!("Hello {}", name);
-///     php_return!(retval, hello);
+///     php_parse_parameters!(data, &mut name_zval);
+///     php_return_result!(retval, String::try_from(name_zval).map(|name| format!("Hello {}", name)));
+/// }
+/// ```
+///
+/// It can also parse any number of typed parameters, passing a spec (see `parse_parameters`) and
+/// the variables that will hold each parameter.
+///
+/// ```no_run
+/// # use solder::*;
+/// use solder::zend::{ExecuteData, Zval};
+/// pub fn repeat(data: &ExecuteData, retval: &mut Zval) {
+///     php_parse_parameters!(data, "sl|s", text: String, times: i64, separator: Option<String>);
+///     let separator = separator.unwrap_or_default();
+///     php_return!(retval, vec![text; times as usize].join(&separator));
 /// }
 /// ```
 #[macro_export]
 macro_rules! php_parse_parameters {
-	($p1:expr) => {
-		[$p1].parse_parameters();
+	($data:expr, $spec:literal, $($name:ident : $type:ty),+ $(,)?) => {
+		let mut parameters = match $crate::zend::parse_parameters($data, $spec) {
+			Ok(parameters) => parameters,
+			Err(_) => return,
+		};
+		$(let $name: $type = match parameters.next_parameter() {
+			Ok(value) => value,
+			Err(_) => return,
+		};)+
+	};
+	($data:expr, $($zval:expr),+ $(,)?) => {
+		if $crate::zend::PhpParseParameters::parse_parameters(&mut [$($zval),+], $data).is_err() {
+			return;
+		}
 	};
-	($p1:expr, $($rest:expr), *) => {
-		[$p1, $($rest), *].parse_parameters();
-	}
-}
-pub trait PhpParseParameters {
-    fn parse_parameters(self: &mut Self);
-}
-
-impl PhpParseParameters for [&mut Zval; 1] {
-    fn parse_parameters(self: &mut Self) {
-        let value_1 = ZendValue{long_value: 0};
-        unsafe {
-            zend_parse_parameters(
-                1,
-                c_str!("z"),
-                &value_1,
-            );
-            add_zend_value_to_zval(value_1, self[0]);
-        }
-    }
-}
-
-impl PhpParseParameters for [&mut Zval; 2] {
-    fn parse_parameters(self: &mut Self) {
-        let value_1 = ZendValue{long_value: 0};
-        let value_2 = ZendValue{long_value: 0};
-        unsafe {
-            zend_parse_parameters(
-                2,
-                c_str!("zz"),
-                &value_1,
-                &value_2,
-            );
-            add_zend_value_to_zval(value_1, self[0]);
-            add_zend_value_to_zval(value_2, self[1]);
-        }
-    }
-}
-
-impl PhpParseParameters for [&mut Zval; 3] {
-    fn parse_parameters(self: &mut Self) {
-        let value_1 = ZendValue{long_value: 0};
-        let value_2 = ZendValue{long_value: 0};
-        let value_3 = ZendValue{long_value: 0};
-        unsafe {
-            zend_parse_parameters(
-                3,
-                c_str!("zzz"),
-                &value_1,
-                &value_2,
-                &value_3,
-            );
-            add_zend_value_to_zval(value_1, self[0]);
-            add_zend_value_to_zval(value_2, self[1]);
-            add_zend_value_to_zval(value_3, self[2]);
-        }
-    }
 }
 
-impl PhpParseParameters for [&mut Zval; 4] {
-    fn parse_parameters(self: &mut Self) {
-        let value_1 = ZendValue{long_value: 0};
-        let value_2 = ZendValue{long_value: 0};
-        let value_3 = ZendValue{long_value: 0};
-        let value_4 = ZendValue{long_value: 0};
-        unsafe {
-            zend_parse_parameters(
-                4,
-                c_str!("zzzz"),
-                &value_1,
-                &value_2,
-                &value_3,
-                &value_4,
-            );
-            add_zend_value_to_zval(value_1, self[0]);
-            add_zend_value_to_zval(value_2, self[1]);
-            add_zend_value_to_zval(value_3, self[2]);
-            add_zend_value_to_zval(value_4, self[3]);
-        }
-    }
+pub trait PhpParseParameters {
+    fn parse_parameters(&mut self, data: &ExecuteData) -> Result<(), ParameterError>;
 }
 
-impl PhpParseParameters for [&mut Zval; 5] {
-    fn parse_parameters(self: &mut Self) {
-        let value_1 = ZendValue{long_value: 0};
-        let value_2 = ZendValue{long_value: 0};
-        let value_3 = ZendValue{long_value: 0};
-        let value_4 = ZendValue{long_value: 0};
-        let value_5 = ZendValue{long_value: 0};
-        unsafe {
-            zend_parse_parameters(
-                5,
-                c_str!("zzzzz"),
-                &value_1,
-                &value_2,
-                &value_3,
-                &value_4,
-                &value_5,
-            );
-            add_zend_value_to_zval(value_1, self[0]);
-            add_zend_value_to_zval(value_2, self[1]);
-            add_zend_value_to_zval(value_3, self[2]);
-            add_zend_value_to_zval(value_4, self[3]);
-            add_zend_value_to_zval(value_5, self[4]);
+impl<const N: usize> PhpParseParameters for [&mut Zval; N] {
+    fn parse_parameters(&mut self, data: &ExecuteData) -> Result<(), ParameterError> {
+        let mut parameters = parse_parameters(data, &"z".repeat(N))?;
+        for zval in self.iter_mut() {
+            **zval = parameters.next_parameter()?;
         }
+        Ok(())
     }
 }
-
-fn add_zend_value_to_zval(value: ZendValue, zval: &mut Zval) {
-    unsafe {
-        let zval_from_value = *value.zval;
-        zval.value = zval_from_value.value;
-        zval.type_info = zval_from_value.type_info;
-        zval.u2 = zval_from_value.u2;
-    }
-}
\ No newline at end of file
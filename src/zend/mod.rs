@@ -1,8 +1,26 @@
 pub use self::module::*;
-pub use self::types::{Zval, FromPhpZval, PhpTypeConversionError, ExecuteData};
+pub use self::class::{ClassBuilder, Class, ClassEntry, PhpClass, find_class, find_registered_class};
+pub use self::exception::*;
+pub use self::panic::PanicHandling;
+pub use self::parameters::*;
+pub use self::types::{Zval, ZvalKind, ZvalRef, ZvalMut, FromPhpZval, PhpTypeConversionError, ExecuteData};
 pub use self::methods::*;
+pub use self::array::{PhpArray, ArrayKey, ArrayIter};
+pub use self::string::PhpString;
+pub use self::globals::module_globals;
+pub use self::ini::{IniEntry, IniEntryBuilder, IniType, INI_USER, INI_PERDIR, INI_SYSTEM, INI_ALL, ini_string, ini_integer, ini_float, ini_bool};
+pub use self::constant::{Constant, ConstantBuilder, ConstantValue, CONST_CS, CONST_PERSISTENT};
 
 mod module;
+mod class;
+mod exception;
+mod panic;
+mod parameters;
 mod types;
 mod internal_php_methods;
 mod methods;
+mod array;
+mod string;
+mod globals;
+mod ini;
+mod constant;
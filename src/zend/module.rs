@@ -1,17 +1,42 @@
 use std;
+use std::ffi::CStr;
 use std::mem;
+use std::sync::Mutex;
 use libc::*;
 
+use super::class::Class;
+use super::constant::{Constant, ConstantBuilder, ConstantValue};
+use super::globals::globals_entry;
+use super::ini::{IniEntry, register_ini_entries};
+use super::internal_php_methods::{display_ini_entries, zend_unregister_ini_entries};
+use super::panic::{PanicHandling, ZeroSized, catch_panic, catch_panic_with_warning, handler_trampoline, raise_pending_fatal_error, set_panic_handling};
 use super::types::*;
+use crate::info::{print_table_start, print_table_row, print_table_end};
 
-type StartupFunc = extern fn (type_: c_int, module_number: c_int) -> c_int;
-type ShutdownFunc = extern fn (type_: c_int, module_number: c_int) -> c_int;
-type InfoFunc = extern fn () ;
-type GlobalsCtorFunc = extern fn (global: *const c_void) -> c_void;
-type GlobalsDtorFunc = extern fn (global: *const c_void) -> c_void;
-type PostDeactivateFunc = extern fn () -> c_int;
-type HandlerFunc = extern fn (execute_data: &ExecuteData, retval: &mut Zval);
+type StartupFunc = extern "C" fn (type_: c_int, module_number: c_int) -> c_int;
+type ShutdownFunc = extern "C" fn (type_: c_int, module_number: c_int) -> c_int;
+type InfoFunc = fn ();
+type HookFunc = fn ();
+type ModuleInfoFunc = extern "C" fn (module: *const Module);
+type GlobalsCtorFunc = extern "C" fn (global: *mut c_void);
+type GlobalsDtorFunc = extern "C" fn (global: *mut c_void);
+type PostDeactivateFunc = extern "C" fn () -> c_int;
+type HandlerFunc = extern "C" fn (execute_data: &ExecuteData, retval: &mut Zval);
 
+/// Method can be called without an object
+pub const ACC_STATIC: u32 = 0x01;
+/// Method can't be overridden
+pub const ACC_FINAL: u32 = 0x04;
+/// Method is visible everywhere
+pub const ACC_PUBLIC: u32 = 0x100;
+/// Method is visible to the class and its children
+pub const ACC_PROTECTED: u32 = 0x200;
+/// Method is visible only to the class
+pub const ACC_PRIVATE: u32 = 0x400;
+pub(crate) const ACC_PPP_MASK: u32 = ACC_PUBLIC | ACC_PROTECTED | ACC_PRIVATE;
+
+/// Information about an argument of a function (`zend_internal_arg_info`)
+#[cfg(not(any(feature = "php72", feature = "php73")))]
 #[repr(C)]
 pub struct ArgInfo {
 	name: *const c_char,
@@ -22,18 +47,45 @@ pub struct ArgInfo {
 	is_variadic: c_char,
 }
 
-/// Information about the arguments of a function
+/// Information about an argument of a function (`zend_internal_arg_info`)
+#[cfg(any(feature = "php72", feature = "php73"))]
+#[repr(C)]
+pub struct ArgInfo {
+	name: *const c_char,
+	/// `zend_type`, a class name or a type code shifted left, with the lowest bit allowing null
+	type_: usize,
+	pass_by_reference: c_char,
+	is_variadic: c_char,
+}
+
 impl ArgInfo {
+	#[cfg(not(any(feature = "php72", feature = "php73")))]
 	pub fn new(name: *const c_char, allow_null: c_char, is_variadic: c_char, by_reference: c_char) -> ArgInfo {
 		ArgInfo {
-			name: name,
+			name,
 			class_name: std::ptr::null(),
 			type_hint: 0,
 			pass_by_reference: by_reference,
-			allow_null: allow_null,
-			is_variadic: is_variadic,
+			allow_null,
+			is_variadic,
 		}
 	}
+
+	#[cfg(any(feature = "php72", feature = "php73"))]
+	pub fn new(name: *const c_char, allow_null: c_char, is_variadic: c_char, by_reference: c_char) -> ArgInfo {
+		ArgInfo {
+			name,
+			type_: allow_null as usize,
+			pass_by_reference: by_reference,
+			is_variadic,
+		}
+	}
+
+	/// The entry before the arguments (`zend_internal_function_info`). It has the same layout,
+	/// with the number of required arguments in place of the name.
+	fn header(required_args: usize) -> ArgInfo {
+		ArgInfo::new(required_args as *const c_char, 0, 0, 0)
+	}
 }
 
 /// Struct with the functions that will be available inside PHP
@@ -43,7 +95,7 @@ pub struct Function {
 	handler: Option<HandlerFunc>,
 	arg_info: *const ArgInfo,
 	num_args: u32,
-	flags: u32,
+	pub(crate) flags: u32,
 }
 
 impl Function {
@@ -61,11 +113,26 @@ impl Function {
 pub struct FunctionBuilder {
 	function: Function,
 	args: Vec<ArgInfo>,
+	required_args: Option<u32>,
 }
 
 impl FunctionBuilder {
-	/// Create a function with name
-	pub fn new(name: *const c_char, handler: HandlerFunc) -> Self {
+	/// Create a function with name. The handler is a Rust function (not an `extern fn`), so panics
+	/// are caught before reaching PHP and reported as configured with `ModuleBuilder::with_panic_handling`.
+	/// Closures are accepted as long as they don't capture anything. Function pointers and capturing
+	/// closures are rejected at compile time, because the handler is recreated from its type alone.
+	pub fn new<F>(name: *const c_char, handler: F) -> Self
+		where F: Fn(&ExecuteData, &mut Zval) + 'static
+	{
+		#[allow(clippy::let_unit_value)]
+		let () = ZeroSized::<F>::CHECK;
+		drop(handler);
+		FunctionBuilder::new_raw(name, handler_trampoline::<F>)
+	}
+
+	/// Create a function with name from an `extern fn` handler that is given directly to PHP.
+	/// A panic inside it can't be caught and aborts the process.
+	pub fn new_raw(name: *const c_char, handler: HandlerFunc) -> Self {
 		FunctionBuilder {
 			function: Function {
 				fname: name,
@@ -75,6 +142,7 @@ impl FunctionBuilder {
 				flags: 0,
 			},
 			args: Vec::new(),
+			required_args: None,
 		}
 	}
 
@@ -84,19 +152,32 @@ impl FunctionBuilder {
 		self
 	}
 
+	/// Set how many of the arguments are required. All of them are by default.
+	pub fn with_required_args(mut self, required_args: u32) -> Self {
+		self.required_args = Some(required_args);
+		self
+	}
+
+	/// Set the flags of the function. Only meaningful for methods, e.g. `ACC_PUBLIC | ACC_STATIC`
+	pub fn with_flags(mut self, flags: u32) -> Self {
+		self.function.flags = flags;
+		self
+	}
+
 	/// Build the function
 	pub fn build(mut self)-> Function {
 		if self.args.is_empty() {
 			return self.function;
 		}
-		self.function.num_args = self.args.len() as u32 - 1;
+		self.function.num_args = self.args.len() as u32;
+		// (zend_uintptr_t)-1 tells the engine that every argument is required
+		let required_args = self.required_args.map_or(usize::MAX, |required_args| required_args as usize);
+		self.args.insert(0, ArgInfo::header(required_args));
 		self.function.arg_info = Box::into_raw(self.args.into_boxed_slice()) as *mut ArgInfo;
 		self.function
 	}
 }
 
-pub struct INI {}
-
 /// Module represents your extension
 #[repr(C)]
 pub struct Module {
@@ -104,7 +185,8 @@ pub struct Module {
 	zend_api: c_uint,
 	zend_debug: c_uchar,
 	zts: c_uchar,
-	ini_entry: *const INI,
+	// Not used by PHP 7, INI entries are registered when the module starts up
+	ini_entry: *const c_void,
 	deps: *const ModuleDep,
 	name: *const c_char,
 	functions: *const Function,
@@ -112,7 +194,7 @@ pub struct Module {
 	module_shutdown_func: Option<ShutdownFunc>,
 	request_startup_func: Option<StartupFunc>,
 	request_shutdown_func: Option<ShutdownFunc>,
-	info_func: Option<InfoFunc>,
+	info_func: Option<ModuleInfoFunc>,
 	version: *const c_char,
 	globals_size: size_t,
 	globals_ptr: *const c_void,
@@ -135,6 +217,200 @@ impl Module {
 pub struct ModuleBuilder {
 	module: Module,
 	functions: Vec<Function>,
+	classes: Vec<Class>,
+	ini_entries: Vec<IniEntry>,
+	constants: Vec<Constant>,
+	info_function: Option<InfoFunc>,
+	startup_hook: Option<HookFunc>,
+	shutdown_hook: Option<HookFunc>,
+	panic_handling: PanicHandling,
+}
+
+/// A function or class declared with `#[php_function]` or `#[php_class]` anywhere in a crate.
+/// `#[php_module]` gives the ones of its crate to `ModuleBuilder::with_crate_items`.
+#[doc(hidden)]
+pub struct CrateItem {
+	pub crate_name: &'static str,
+	pub module_path: &'static str,
+	pub line: u32,
+	pub kind: CrateItemKind,
+}
+
+#[doc(hidden)]
+pub enum CrateItemKind {
+	Function(fn () -> FunctionBuilder),
+	Class(fn () -> Class),
+}
+
+inventory::collect!(CrateItem);
+
+/// What solder needs to register when the module starts up and to show in PHP Info
+struct ModuleState {
+	startup_function: Option<StartupFunc>,
+	shutdown_function: Option<ShutdownFunc>,
+	startup_hook: Option<HookFunc>,
+	shutdown_hook: Option<HookFunc>,
+	info_function: Option<InfoFunc>,
+	classes: Vec<Class>,
+	ini_entries: Vec<IniEntry>,
+	constants: Vec<Constant>,
+	function_names: Vec<String>,
+	class_names: Vec<String>,
+}
+
+static MODULE_STATE: Mutex<Option<ModuleState>> = Mutex::new(None);
+
+/// Returned by the lifecycle functions of the module
+pub(crate) const SUCCESS: c_int = 0;
+pub(crate) const FAILURE: c_int = -1;
+
+/// Registers the INI entries, constants and classes, then runs the startup function and hook.
+/// A panic while registering them, like a missing parent class, or in the hook is reported and
+/// makes the startup fail.
+extern "C" fn module_startup(type_: c_int, module_number: c_int) -> c_int {
+	let result = match catch_panic(|| register_module_items(module_number)) {
+		Some((startup_function, startup_hook)) => {
+			let result = match startup_function {
+				Some(startup_function) => startup_function(type_, module_number),
+				None => SUCCESS,
+			};
+			match startup_hook {
+				Some(startup_hook) if result == SUCCESS => run_hook(catch_panic(startup_hook)),
+				_ => result,
+			}
+		},
+		None => FAILURE,
+	};
+	raise_pending_fatal_error();
+	result
+}
+
+/// Registers what the module declares and returns its startup function and hook
+fn register_module_items(module_number: c_int) -> (Option<StartupFunc>, Option<HookFunc>) {
+	let (classes, ini_entries, constants, startup_function, startup_hook) = match MODULE_STATE.lock().unwrap().as_mut() {
+		Some(state) => (mem::take(&mut state.classes), mem::take(&mut state.ini_entries), mem::take(&mut state.constants), state.startup_function, state.startup_hook),
+		None => return (None, None),
+	};
+	if !ini_entries.is_empty() {
+		register_ini_entries(ini_entries, module_number);
+	}
+	for constant in constants {
+		constant.register(module_number);
+	}
+	let order = parents_first(&classes.iter().map(|class| (class.name(), class.parent_name())).collect::<Vec<_>>());
+	let mut classes: Vec<Option<Class>> = classes.into_iter().map(Some).collect();
+	for index in order {
+		if let Some(class) = classes[index].take() {
+			class.register();
+		}
+	}
+	(startup_function, startup_hook)
+}
+
+/// Returns the order in which to register classes, given as their names and parents, so that the
+/// parents declared by the module come before their children
+fn parents_first(classes: &[(&str, Option<&str>)]) -> Vec<usize> {
+	let mut waiting: Vec<usize> = (0..classes.len()).collect();
+	let mut order = Vec::with_capacity(classes.len());
+	while !waiting.is_empty() {
+		let declared = |parent: &str| waiting.iter().any(|&index| classes[index].0.eq_ignore_ascii_case(parent.trim_start_matches('\\')));
+		// A class whose parent is missing or in a cycle fails when it is registered
+		let next = waiting.iter()
+			.position(|&index| !classes[index].1.is_some_and(declared))
+			.unwrap_or(0);
+		order.push(waiting.remove(next));
+	}
+	order
+}
+
+fn run_hook(result: Option<()>) -> c_int {
+	match result {
+		Some(()) => SUCCESS,
+		None => FAILURE,
+	}
+}
+
+/// Runs the shutdown hook and function. The engine can't bail out of a fatal error at this point,
+/// so a panic in the hook is reported with a warning.
+extern "C" fn module_shutdown(type_: c_int, module_number: c_int) -> c_int {
+	let (shutdown_function, shutdown_hook) = match MODULE_STATE.lock().unwrap().as_ref() {
+		Some(state) => (state.shutdown_function, state.shutdown_hook),
+		None => (None, None),
+	};
+	let hook_result = match shutdown_hook {
+		Some(shutdown_hook) => run_hook(catch_panic_with_warning(shutdown_hook)),
+		None => SUCCESS,
+	};
+	let result = match shutdown_function {
+		Some(shutdown_function) => shutdown_function(type_, module_number),
+		None => SUCCESS,
+	};
+	unsafe {
+		zend_unregister_ini_entries(module_number);
+	}
+	if hook_result == SUCCESS { result } else { hook_result }
+}
+
+/// Prints the module in PHP Info: a table describing it, then the rows of the info function and
+/// the INI entries with their local and master values
+extern "C" fn module_info(module: *const Module) {
+	let info_function = match MODULE_STATE.lock().unwrap().as_ref() {
+		Some(state) => state.info_function,
+		None => return,
+	};
+	print_module_table(module);
+	if let Some(info_function) = info_function {
+		catch_panic(info_function);
+	}
+	unsafe {
+		display_ini_entries(module as *const c_void);
+	}
+	raise_pending_fatal_error();
+}
+
+/// Prints the table shown for every module, with its version, functions and classes
+fn print_module_table(module: *const Module) {
+	let (function_names, class_names) = match MODULE_STATE.lock().unwrap().as_ref() {
+		Some(state) => (state.function_names.join(", "), state.class_names.join(", ")),
+		None => return,
+	};
+	print_table_start();
+	unsafe {
+		print_table_row(&format!("{} support", c_name_to_string((*module).name)), "enabled");
+		print_table_row("Version", &c_name_to_string((*module).version));
+	}
+	print_table_row("Solder version", env!("CARGO_PKG_VERSION"));
+	print_table_row("PHP API", env!("PHP_API_VERSION"));
+	if !function_names.is_empty() {
+		print_table_row("Functions", &function_names);
+	}
+	if !class_names.is_empty() {
+		print_table_row("Classes", &class_names);
+	}
+	print_table_end();
+}
+
+/// The items registered by a crate, in the order of their modules and lines
+fn crate_items(crate_name: &str) -> Vec<&'static CrateItem> {
+	let mut items: Vec<&CrateItem> = inventory::iter::<CrateItem>
+		.into_iter()
+		.filter(|item| item.crate_name == crate_name)
+		.collect();
+	items.sort_by_key(|item| (item.module_path, item.line));
+	items
+}
+
+/// Calls a function from its type alone, which `ZeroSized` checked to hold nothing
+fn call_zero_sized<F: Fn()>() {
+	let func: F = unsafe { mem::zeroed() };
+	func()
+}
+
+fn c_name_to_string(name: *const c_char) -> String {
+	if name.is_null() {
+		return String::new();
+	}
+	unsafe { CStr::from_ptr(name) }.to_string_lossy().into_owned()
 }
 
 impl ModuleBuilder {
@@ -145,17 +421,17 @@ impl ModuleBuilder {
 				size: mem::size_of::<Module>() as u16,
 				zend_api: env!("PHP_API_VERSION").parse::<u32>().unwrap(),
 				zend_debug: 0,
-				zts: 0,
+				zts: cfg!(feature = "zts") as c_uchar,
 				ini_entry: std::ptr::null(),
 				deps: std::ptr::null(),
-				name: name,
+				name,
 				functions: std::ptr::null(),
 				module_startup_func: None,
 				module_shutdown_func: None,
 				request_startup_func: None,
 				request_shutdown_func: None,
 				info_func: None,
-				version: version,
+				version,
 				globals_size: 0,
 				globals_ptr: std::ptr::null(),
 				globals_ctor: None,
@@ -168,6 +444,13 @@ impl ModuleBuilder {
 				build_id: c_str!(env!("PHP_EXTENSION_BUILD")),
 			},
 			functions: Vec::new(),
+			classes: Vec::new(),
+			ini_entries: Vec::new(),
+			constants: Vec::new(),
+			info_function: None,
+			startup_hook: None,
+			shutdown_hook: None,
+			panic_handling: PanicHandling::ThrowError,
 		}
 	}
 
@@ -183,9 +466,55 @@ impl ModuleBuilder {
 		self
 	}
 
-	/// Set a function to print information in PHP Info
-	pub fn with_info_function(mut self, func: InfoFunc) -> Self {
-		self.module.info_func = Some(func);
+	/// Set a Rust function to run when the module starts up, after its INI entries, constants and
+	/// classes are registered. Like handlers, it can be a function or a closure that captures
+	/// nothing. Its panics are reported as configured with `with_panic_handling` and make the
+	/// startup fail.
+	pub fn with_startup_hook<F>(mut self, func: F) -> Self
+		where F: Fn() + 'static
+	{
+		#[allow(clippy::let_unit_value)]
+		let () = ZeroSized::<F>::CHECK;
+		drop(func);
+		self.startup_hook = Some(call_zero_sized::<F>);
+		self
+	}
+
+	/// Set a Rust function to run when the module shuts down, like `with_startup_hook`. Its panics
+	/// are reported with a warning and make the shutdown fail.
+	pub fn with_shutdown_hook<F>(mut self, func: F) -> Self
+		where F: Fn() + 'static
+	{
+		#[allow(clippy::let_unit_value)]
+		let () = ZeroSized::<F>::CHECK;
+		drop(func);
+		self.shutdown_hook = Some(call_zero_sized::<F>);
+		self
+	}
+
+	/// Set a function to print information in PHP Info. Its rows come after the table that solder
+	/// prints for every module, with the version, functions and classes, and before the INI entries.
+	/// Like handlers, it can be a function or a closure that captures nothing, and its panics are
+	/// reported as configured with `with_panic_handling`.
+	pub fn with_info_function<F>(mut self, func: F) -> Self
+		where F: Fn() + 'static
+	{
+		#[allow(clippy::let_unit_value)]
+		let () = ZeroSized::<F>::CHECK;
+		drop(func);
+		self.info_function = Some(call_zero_sized::<F>);
+		self
+	}
+
+	/// Give the module globals of type `T`, created with `T::default()` when the module is loaded
+	/// and dropped when it is unloaded. Read and change them with `module_globals`.
+	/// A module has one set of globals, so it panics if called twice.
+	pub fn with_globals<T: Default + 'static>(mut self) -> Self {
+		let globals = globals_entry::<T>();
+		self.module.globals_size = globals.size;
+		self.module.globals_ptr = globals.location;
+		self.module.globals_ctor = Some(globals.ctor);
+		self.module.globals_dtor = Some(globals.dtor);
 		self
 	}
 
@@ -195,11 +524,130 @@ impl ModuleBuilder {
 		self
 	}
 
+	/// Set classes that will be available from PHP. They are registered when the module starts up.
+	pub fn with_class(mut self, class: Class) -> Self {
+		self.classes.push(class);
+		self
+	}
+
+	/// Declare an exception class, usually built with `ExceptionBuilder`.
+	/// Classes are registered in order, except that parents declared by the module come first.
+	pub fn with_exception(self, exception: Class) -> Self {
+		self.with_class(exception)
+	}
+
+	/// Declare an INI entry, built with `IniEntryBuilder`. Entries are registered when the module
+	/// starts up and their values are read with `ini_string`, `ini_integer`, `ini_float` and `ini_bool`.
+	pub fn with_ini_entry(mut self, entry: IniEntry) -> Self {
+		self.ini_entries.push(entry);
+		self
+	}
+
+	/// Declare a case sensitive constant. The value can be null, a bool, an integer, a float or a
+	/// string, see `ConstantValue`.
+	/// ```no_run
+	/// # use solder::*;
+	/// # use solder::zend::ModuleBuilder;
+	/// # let builder = ModuleBuilder::new(c_str!("myext"), c_str!("1.0.0"));
+	/// builder.with_constant(c_str!("MYEXT_VERSION"), "1.0.0").with_constant(c_str!("MYEXT_MODE_FAST"), 1)
+	/// # ;
+	/// ```
+	pub fn with_constant<T: ConstantValue>(self, name: *const c_char, value: T) -> Self {
+		self.with_constant_entry(ConstantBuilder::new(name, value).build())
+	}
+
+	/// Declare a constant built with `ConstantBuilder`, to choose its namespace and flags.
+	/// Constants are registered when the module starts up.
+	pub fn with_constant_entry(mut self, constant: Constant) -> Self {
+		self.constants.push(constant);
+		self
+	}
+
+	/// Add the functions and classes declared with `#[php_function]` and `#[php_class]` anywhere in
+	/// the crate, in the order of their modules and lines. `#[php_module]` calls it with the name of
+	/// its crate.
+	#[doc(hidden)]
+	pub fn with_crate_items(mut self, crate_name: &str) -> Self {
+		for item in crate_items(crate_name) {
+			self = match item.kind {
+				CrateItemKind::Function(builder) => self.with_function(builder().build()),
+				CrateItemKind::Class(class) => self.with_class(class()),
+			};
+		}
+		self
+	}
+
+	/// Choose what happens when a handler panics. By default a `\Error` is thrown.
+	pub fn with_panic_handling(mut self, panic_handling: PanicHandling) -> Self {
+		self.panic_handling = panic_handling;
+		self
+	}
+
 	pub fn build(mut self) -> Module {
+		set_panic_handling(self.panic_handling);
+		let function_names = self.functions.iter().map(|function| c_name_to_string(function.fname)).collect();
 		self.functions.push(Function::end());
 		self.module.functions = Box::into_raw(self.functions.into_boxed_slice()) as *const Function;
+		*MODULE_STATE.lock().unwrap() = Some(ModuleState {
+			startup_function: self.module.module_startup_func,
+			shutdown_function: self.module.module_shutdown_func,
+			startup_hook: self.startup_hook,
+			shutdown_hook: self.shutdown_hook,
+			info_function: self.info_function,
+			function_names,
+			class_names: self.classes.iter().map(|class| class.name().to_string()).collect(),
+			classes: self.classes,
+			ini_entries: self.ini_entries,
+			constants: self.constants,
+		});
+		self.module.module_startup_func = Some(module_startup);
+		self.module.module_shutdown_func = Some(module_shutdown);
+		self.module.info_func = Some(module_info);
 		self.module
 	}
 }
 
 unsafe impl Sync for Module {}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn class() -> Class {
+		unreachable!("Only the order of the items is tested")
+	}
+
+	inventory::submit! {
+		CrateItem { crate_name: "shop", module_path: "shop::models", line: 12, kind: CrateItemKind::Class(class) }
+	}
+
+	inventory::submit! {
+		CrateItem { crate_name: "shop", module_path: "shop::models", line: 3, kind: CrateItemKind::Class(class) }
+	}
+
+	inventory::submit! {
+		CrateItem { crate_name: "shop", module_path: "shop::errors", line: 8, kind: CrateItemKind::Class(class) }
+	}
+
+	inventory::submit! {
+		CrateItem { crate_name: "other", module_path: "other", line: 1, kind: CrateItemKind::Class(class) }
+	}
+
+	#[test]
+	fn crate_items_are_in_module_and_line_order() {
+		let items: Vec<(&str, u32)> = crate_items("shop").iter().map(|item| (item.module_path, item.line)).collect();
+		assert_eq!(items, [("shop::errors", 8), ("shop::models", 3), ("shop::models", 12)]);
+	}
+
+	#[test]
+	fn parents_are_registered_before_their_children() {
+		let classes = [
+			("Shop\\Child", Some("\\shop\\parent")),
+			("Shop\\Other", Some("Exception")),
+			("Shop\\Parent", None),
+			("Shop\\Loop", Some("Shop\\Loop")),
+		];
+		assert_eq!(parents_first(&classes), [1, 2, 0, 3]);
+	}
+}
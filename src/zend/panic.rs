@@ -0,0 +1,144 @@
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::mem;
+use libc::*;
+
+use super::internal_php_methods::*;
+use super::types::*;
+
+const E_ERROR: c_int = 1;
+const E_WARNING: c_int = 2;
+
+/// What PHP sees when a handler panics
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PanicHandling {
+	/// Throws a `\Error` with the panic message and location, that can be caught by PHP
+	ThrowError = 0,
+	/// Raises a fatal error with the panic message and location, ending the request
+	FatalError = 1,
+}
+
+static PANIC_HANDLING: AtomicU8 = AtomicU8::new(PanicHandling::ThrowError as u8);
+static PANIC_HOOK: Once = Once::new();
+
+thread_local! {
+	static PANIC_LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+	/// Message of the fatal error waiting for `raise_pending_fatal_error`. It stays here while the
+	/// engine formats it, so nothing is left to free when the error bails out.
+	static FATAL_MESSAGE: RefCell<CString> = RefCell::new(CString::default());
+	static FATAL_PENDING: Cell<bool> = const { Cell::new(false) };
+}
+
+pub(crate) fn set_panic_handling(panic_handling: PanicHandling) {
+	PANIC_HANDLING.store(panic_handling as u8, Ordering::Relaxed);
+	PANIC_HOOK.call_once(|| {
+		let previous_hook = panic::take_hook();
+		panic::set_hook(Box::new(move |info| {
+			let location = info.location().map(|location| format!("{}:{}", location.file(), location.line()));
+			PANIC_LOCATION.with(|panic_location| *panic_location.borrow_mut() = location);
+			previous_hook(info);
+		}));
+	});
+}
+
+/// Fails to compile when `F` is not zero sized, so handlers can be created from their type alone.
+/// Function items and closures capturing nothing are zero sized, function pointers and closures
+/// capturing variables are not.
+pub(crate) struct ZeroSized<F>(PhantomData<F>);
+
+impl<F> ZeroSized<F> {
+	pub(crate) const CHECK: () = assert!(
+		mem::size_of::<F>() == 0,
+		"Handlers must be function items or closures capturing nothing, not function pointers or capturing closures",
+	);
+}
+
+/// Handler given to PHP for every function built with `FunctionBuilder::new`.
+/// It calls the Rust handler and stops any panic from unwinding into the engine.
+pub(crate) extern "C" fn handler_trampoline<F>(execute_data: &ExecuteData, retval: &mut Zval)
+	where F: Fn(&ExecuteData, &mut Zval)
+{
+	catch_panic(|| {
+		// Handlers are function items or closures without captures, so there is nothing to initialize
+		let handler: F = unsafe { mem::zeroed() };
+		handler(execute_data, retval)
+	});
+	raise_pending_fatal_error();
+}
+
+/// Runs `f` and reports a panic to PHP instead of letting it unwind into the engine.
+/// Returns `None` if it panicked.
+///
+/// With `PanicHandling::FatalError`, the error is only raised by `raise_pending_fatal_error`.
+/// When no PHP code is running to catch an exception, a warning is raised instead.
+pub(crate) fn catch_panic<R>(f: impl FnOnce() -> R) -> Option<R> {
+	match panic::catch_unwind(AssertUnwindSafe(f)) {
+		Ok(value) => Some(value),
+		Err(payload) => {
+			report_panic(payload, true);
+			None
+		},
+	}
+}
+
+/// Like `catch_panic`, but never raises a fatal error, for code running when the engine can't bail
+/// out of one, like the module shutdown. Without PHP code running, the panic is a warning.
+pub(crate) fn catch_panic_with_warning<R>(f: impl FnOnce() -> R) -> Option<R> {
+	match panic::catch_unwind(AssertUnwindSafe(f)) {
+		Ok(value) => Some(value),
+		Err(payload) => {
+			report_panic(payload, false);
+			None
+		},
+	}
+}
+
+/// Raises the fatal error of a panic caught by `catch_panic`, if any.
+///
+/// The engine bails out of a fatal error with a `longjmp` back to its own frames. Skipping a Rust
+/// frame is only sound when it has nothing left to drop, so this must be the last call of the
+/// `extern "C"` function given to the engine, after every other Rust value is dropped.
+pub(crate) fn raise_pending_fatal_error() {
+	if !FATAL_PENDING.with(|pending| pending.replace(false)) {
+		return;
+	}
+	let message = FATAL_MESSAGE.with(|message| message.borrow().as_ptr());
+	unsafe {
+		zend_error(E_ERROR, c_str!("%s"), message);
+	}
+}
+
+fn report_panic(payload: Box<dyn Any + Send>, fatal_allowed: bool) {
+	let message = panic_message(payload.as_ref(), PANIC_LOCATION.with(|panic_location| panic_location.borrow_mut().take()));
+	drop(payload);
+	let c_message = CString::new(message.replace('\0', "")).unwrap();
+	let executing = unsafe { zend_is_executing() != 0 };
+	if fatal_allowed && PANIC_HANDLING.load(Ordering::Relaxed) == PanicHandling::FatalError as u8 {
+		FATAL_MESSAGE.with(|fatal_message| *fatal_message.borrow_mut() = c_message);
+		FATAL_PENDING.with(|pending| pending.set(true));
+	} else if executing {
+		unsafe { zend_throw_exception(zend_ce_error, c_message.as_ptr(), 0) };
+	} else {
+		// An exception needs PHP code to catch it, like during startup or `php -i`
+		unsafe { zend_error(E_WARNING, c_str!("%s"), c_message.as_ptr()) };
+	}
+}
+
+fn panic_message(payload: &(dyn Any + Send), location: Option<String>) -> String {
+	let message = match payload.downcast_ref::<&str>() {
+		Some(message) => message.to_string(),
+		None => match payload.downcast_ref::<String>() {
+			Some(message) => message.clone(),
+			None => "Box<Any>".to_string(),
+		},
+	};
+	match location {
+		Some(location) => format!("Rust panic: {} at {}", message, location),
+		None => format!("Rust panic: {}", message),
+	}
+}
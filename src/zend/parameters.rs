@@ -0,0 +1,502 @@
+use std::borrow::Cow;
+use std::ptr;
+use libc::*;
+
+use super::array::PhpArray;
+use super::exception::throw_exception;
+use super::internal_php_methods::*;
+use super::types::*;
+
+// zend_expected_type, used by PHP to build the error message of a wrong parameter
+const EXPECTED_LONG: c_int = 0;
+const EXPECTED_BOOL: c_int = 1;
+const EXPECTED_STRING: c_int = 2;
+const EXPECTED_ARRAY: c_int = 3;
+const EXPECTED_FUNC: c_int = 4;
+const EXPECTED_OBJECT: c_int = 7;
+const EXPECTED_DOUBLE: c_int = 8;
+
+/// A parameter parsed by `parse_parameters`, already converted to the type asked by the spec
+pub enum Parameter<'a> {
+    /// `l`
+    Long(i64),
+    /// `d`
+    Double(f64),
+    /// `b`
+    Bool(bool),
+    /// `s`, borrowed from the argument. Can be read as `&[u8]` or `Vec<u8>`, and as `&str`, `Cow<str>`
+    /// or `String` when it is valid UTF-8.
+    String(&'a [u8]),
+    /// `a`
+    Array(ZvalRef<'a>),
+    /// `h`, borrowed from the argument
+    HashTable(&'a PhpArray),
+    /// `f`. Can be called with `execute_closure`
+    Callable(ZvalRef<'a>),
+    /// `o`
+    Object(ZvalRef<'a>),
+    /// `z`
+    Zval(ZvalRef<'a>),
+    /// `z/`, to change the argument. Assigning it changes the variable of the caller when the
+    /// argument is passed by reference.
+    ZvalMut(ZvalMut<'a>),
+    /// `*` or `+`
+    Variadic(Vec<ZvalRef<'a>>),
+    /// An optional parameter that was not passed or a nullable parameter (`!`) that received null
+    Missing,
+}
+
+/// Errors returned when the parameters don't match the spec.
+/// PHP has already emitted the warning or exception for them, so the handler just needs to return.
+#[derive(Debug)]
+pub enum ParameterError {
+    WrongCount { passed: u32, min: u32, max: Option<u32> },
+    WrongType { position: u32 },
+    /// The handler asked for a type that the spec does not give, like an `i64` for a `s`
+    /// parameter, or for more parameters than the spec has. An `\Error` was thrown.
+    NotInSpec { position: u32 },
+    /// A string was read as `&str`, `Cow<str>` or `String` but is not valid UTF-8.
+    /// A `TypeError` was thrown.
+    InvalidUtf8 { position: u32 },
+}
+
+struct SpecItem {
+    kind: char,
+    nullable: bool,
+    writable: bool,
+}
+
+/// Parses the parameters passed to the function following a spec similar to the one used by
+/// `zend_parse_parameters`:
+///
+/// - `l` integer, `d` float, `b` bool, `s` string
+/// - `a` array as a Zval, `h` array as a `PhpArray`, `o` object, `f` callable, `z` any Zval
+/// - `!` after one of them makes it nullable, giving `Parameter::Missing` for null
+/// - `/` after `z` gives the argument as a `ZvalMut`, used for arguments passed by reference
+/// - `|` the parameters after it are optional
+/// - `*` zero or more, and `+` one or more remaining parameters. Must be the last one.
+///
+/// PHP's weak and strict typing rules are applied to scalars, so `"5"` is accepted as `l` in weak mode.
+/// Arguments passed by reference are read through the reference. An array given to `z/` by value
+/// is copied first if the caller shares it, so changing it only changes the copy of the function.
+pub fn parse_parameters<'a>(data: &'a ExecuteData, spec: &str) -> Result<Parameters<'a>, ParameterError> {
+    let (items, min, max) = parse_spec(spec);
+    let passed = data.arguments_count();
+    if passed < min || max.is_some_and(|max| passed > max) {
+        wrong_parameters_count(passed, min, max);
+        return Err(ParameterError::WrongCount { passed, min, max });
+    }
+    let mut parameters = Vec::with_capacity(items.len());
+    let mut position = 0;
+    for item in items {
+        if item.kind == '*' || item.kind == '+' {
+            let rest = (position..passed).map(|index| unsafe { ZvalRef::from_ptr(data.argument_pointer(index)) }).collect();
+            parameters.push(Parameter::Variadic(rest));
+            position = passed;
+            continue;
+        }
+        if position >= passed {
+            parameters.push(Parameter::Missing);
+            continue;
+        }
+        let slot = data.argument_pointer(position);
+        if item.writable && unsafe { !(*slot).is_reference() } {
+            // Like SEPARATE_ZVAL_NOREF, an array passed by value may be shared with the caller
+            unsafe { (*slot).separate_array() };
+        }
+        let argument: *mut Zval = unsafe { (*slot).dereference_mut() };
+        position += 1;
+        if item.nullable && unsafe { (*argument).is_null() } {
+            parameters.push(Parameter::Missing);
+            continue;
+        }
+        if item.writable {
+            parameters.push(Parameter::ZvalMut(unsafe { ZvalMut::from_ptr(argument) }));
+            continue;
+        }
+        match parse_argument(item.kind, argument) {
+            Some(parameter) => parameters.push(parameter),
+            None => {
+                wrong_parameter_type(position, expected_type(item.kind), argument);
+                return Err(ParameterError::WrongType { position });
+            },
+        }
+    }
+    Ok(Parameters { data, parameters: parameters.into_iter(), position: 0 })
+}
+
+/// Parameters returned by `parse_parameters`, consumed in order.
+pub struct Parameters<'a> {
+    data: &'a ExecuteData,
+    parameters: std::vec::IntoIter<Parameter<'a>>,
+    position: u32,
+}
+
+impl<'a> Parameters<'a> {
+    /// Returns the next parameter converted to a Rust type.
+    /// A string that is not valid UTF-8 read as `&str`, `Cow<str>` or `String` throws a `TypeError`
+    /// and returns an error, so the handler just needs to return.
+    /// Asking a type that does not match the spec, like an `i64` for a `s` parameter, throws an
+    /// `\Error` and returns an error too.
+    pub fn next_parameter<T: FromParameter<'a>>(&mut self) -> Result<T, ParameterError> {
+        self.position += 1;
+        let parameter = match self.parameters.next() {
+            Some(parameter) => parameter,
+            None => return Err(self.not_in_spec("there are no parameters left in the spec")),
+        };
+        match T::from_parameter(parameter) {
+            Ok(value) => Ok(value),
+            Err(FromParameterError::InvalidUtf8) => {
+                let message = format!("{}() expects parameter {} to be a valid UTF-8 string", self.function_name(), self.position);
+                unsafe { throw_exception(zend_ce_type_error, &message, 0) };
+                Err(ParameterError::InvalidUtf8 { position: self.position })
+            },
+            Err(FromParameterError::WrongKind(_)) => Err(self.not_in_spec("its type does not match the spec")),
+        }
+    }
+
+    fn not_in_spec(&self, reason: &str) -> ParameterError {
+        let message = format!("{}() can't read parameter {}, {}", self.function_name(), self.position, reason);
+        unsafe { throw_exception(zend_ce_error, &message, 0) };
+        ParameterError::NotInSpec { position: self.position }
+    }
+
+    fn function_name(&self) -> String {
+        let function_name = self.data.function_name().unwrap_or_default();
+        match self.data.scope_name() {
+            Some(class_name) => format!("{}::{}", class_name, function_name),
+            None => function_name,
+        }
+    }
+}
+
+/// Why `FromParameter` could not convert a parameter
+pub enum FromParameterError<'a> {
+    /// The type does not match the spec, like asking an `i64` for a `s` parameter
+    WrongKind(Parameter<'a>),
+    /// The string is not valid UTF-8. Binary strings can be read as `&[u8]` or `Vec<u8>`.
+    InvalidUtf8,
+}
+
+impl<'a> From<Parameter<'a>> for FromParameterError<'a> {
+    fn from(parameter: Parameter<'a>) -> Self {
+        FromParameterError::WrongKind(parameter)
+    }
+}
+
+/// Converts a parsed `Parameter` into a Rust type. Use `Option<T>` for optional and nullable parameters.
+/// Zvals can be borrowed as `ZvalRef`, or copied as `Zval`.
+pub trait FromParameter<'a>: Sized {
+    fn from_parameter(parameter: Parameter<'a>) -> Result<Self, FromParameterError<'a>>;
+}
+
+impl<'a> FromParameter<'a> for i64 {
+    fn from_parameter(parameter: Parameter<'a>) -> Result<Self, FromParameterError<'a>> {
+        match parameter {
+            Parameter::Long(value) => Ok(value),
+            parameter => Err(parameter.into()),
+        }
+    }
+}
+
+impl<'a> FromParameter<'a> for f64 {
+    fn from_parameter(parameter: Parameter<'a>) -> Result<Self, FromParameterError<'a>> {
+        match parameter {
+            Parameter::Double(value) => Ok(value),
+            parameter => Err(parameter.into()),
+        }
+    }
+}
+
+impl<'a> FromParameter<'a> for bool {
+    fn from_parameter(parameter: Parameter<'a>) -> Result<Self, FromParameterError<'a>> {
+        match parameter {
+            Parameter::Bool(value) => Ok(value),
+            parameter => Err(parameter.into()),
+        }
+    }
+}
+
+/// Fails with `InvalidUtf8` if the string is not valid UTF-8
+impl<'a> FromParameter<'a> for String {
+    fn from_parameter(parameter: Parameter<'a>) -> Result<Self, FromParameterError<'a>> {
+        <&str>::from_parameter(parameter).map(str::to_string)
+    }
+}
+
+/// Borrows the string without copying it. Fails with `InvalidUtf8` if it is not valid UTF-8.
+impl<'a> FromParameter<'a> for &'a str {
+    fn from_parameter(parameter: Parameter<'a>) -> Result<Self, FromParameterError<'a>> {
+        match parameter {
+            Parameter::String(value) => std::str::from_utf8(value).map_err(|_| FromParameterError::InvalidUtf8),
+            parameter => Err(parameter.into()),
+        }
+    }
+}
+
+/// Borrows the string without copying it. Fails with `InvalidUtf8` if it is not valid UTF-8.
+impl<'a> FromParameter<'a> for Cow<'a, str> {
+    fn from_parameter(parameter: Parameter<'a>) -> Result<Self, FromParameterError<'a>> {
+        <&str>::from_parameter(parameter).map(Cow::Borrowed)
+    }
+}
+
+impl<'a> FromParameter<'a> for &'a [u8] {
+    fn from_parameter(parameter: Parameter<'a>) -> Result<Self, FromParameterError<'a>> {
+        match parameter {
+            Parameter::String(value) => Ok(value),
+            parameter => Err(parameter.into()),
+        }
+    }
+}
+
+impl<'a> FromParameter<'a> for Vec<u8> {
+    fn from_parameter(parameter: Parameter<'a>) -> Result<Self, FromParameterError<'a>> {
+        match parameter {
+            Parameter::String(value) => Ok(value.to_vec()),
+            parameter => Err(parameter.into()),
+        }
+    }
+}
+
+impl<'a> FromParameter<'a> for &'a PhpArray {
+    fn from_parameter(parameter: Parameter<'a>) -> Result<Self, FromParameterError<'a>> {
+        match parameter {
+            Parameter::HashTable(value) => Ok(value),
+            parameter => Err(parameter.into()),
+        }
+    }
+}
+
+impl<'a> FromParameter<'a> for Zval {
+    fn from_parameter(parameter: Parameter<'a>) -> Result<Self, FromParameterError<'a>> {
+        match parameter {
+            Parameter::Array(value) | Parameter::Callable(value) | Parameter::Object(value) | Parameter::Zval(value) => Ok(value.to_zval()),
+            parameter => Err(parameter.into()),
+        }
+    }
+}
+
+impl<'a> FromParameter<'a> for ZvalRef<'a> {
+    fn from_parameter(parameter: Parameter<'a>) -> Result<Self, FromParameterError<'a>> {
+        match parameter {
+            Parameter::Array(value) | Parameter::Callable(value) | Parameter::Object(value) | Parameter::Zval(value) => Ok(value),
+            parameter => Err(parameter.into()),
+        }
+    }
+}
+
+impl<'a> FromParameter<'a> for ZvalMut<'a> {
+    fn from_parameter(parameter: Parameter<'a>) -> Result<Self, FromParameterError<'a>> {
+        match parameter {
+            Parameter::ZvalMut(value) => Ok(value),
+            parameter => Err(parameter.into()),
+        }
+    }
+}
+
+impl<'a> FromParameter<'a> for Vec<Zval> {
+    fn from_parameter(parameter: Parameter<'a>) -> Result<Self, FromParameterError<'a>> {
+        match parameter {
+            Parameter::Variadic(values) => Ok(values.iter().map(|value| value.to_zval()).collect()),
+            parameter => Err(parameter.into()),
+        }
+    }
+}
+
+impl<'a> FromParameter<'a> for Vec<ZvalRef<'a>> {
+    fn from_parameter(parameter: Parameter<'a>) -> Result<Self, FromParameterError<'a>> {
+        match parameter {
+            Parameter::Variadic(values) => Ok(values),
+            parameter => Err(parameter.into()),
+        }
+    }
+}
+
+impl<'a, T: FromParameter<'a>> FromParameter<'a> for Option<T> {
+    fn from_parameter(parameter: Parameter<'a>) -> Result<Self, FromParameterError<'a>> {
+        match parameter {
+            Parameter::Missing => Ok(None),
+            parameter => T::from_parameter(parameter).map(Some),
+        }
+    }
+}
+
+impl<'a> FromParameter<'a> for Parameter<'a> {
+    fn from_parameter(parameter: Parameter<'a>) -> Result<Self, FromParameterError<'a>> {
+        Ok(parameter)
+    }
+}
+
+/// Returns the items of the spec with the minimum and maximum (None if variadic) number of arguments
+fn parse_spec(spec: &str) -> (Vec<SpecItem>, u32, Option<u32>) {
+    let mut items: Vec<SpecItem> = Vec::new();
+    let mut min = 0;
+    let mut optional = false;
+    let mut variadic = false;
+    for kind in spec.chars() {
+        match kind {
+            'l' | 'd' | 'b' | 's' | 'a' | 'h' | 'f' | 'o' | 'z' => {
+                assert!(!variadic, "Variadic parameters must be the last ones in the spec {}", spec);
+                items.push(SpecItem { kind, nullable: false, writable: false });
+                if !optional {
+                    min += 1;
+                }
+            },
+            '!' => items.last_mut().expect("Nullable modifier without a parameter").nullable = true,
+            '/' => {
+                let item = items.last_mut().filter(|item| item.kind == 'z').expect("The / modifier must follow z");
+                item.writable = true;
+            },
+            '|' => optional = true,
+            '*' | '+' => {
+                if kind == '+' && !optional {
+                    min += 1;
+                }
+                variadic = true;
+                items.push(SpecItem { kind, nullable: false, writable: false });
+            },
+            _ => panic!("Unknown parameter type {} in spec {}", kind, spec),
+        }
+    }
+    let max = if variadic { None } else { Some(items.len() as u32) };
+    (items, min, max)
+}
+
+fn parse_argument<'a>(kind: char, argument: *mut Zval) -> Option<Parameter<'a>> {
+    unsafe {
+        let zval: &'a Zval = &*argument;
+        match kind {
+            'l' => {
+                if zval.is_integer() {
+                    return Some(Parameter::Long(zval.value.long_value));
+                }
+                let mut value: i64 = 0;
+                parsed(zend_parse_arg_long_slow(argument, &mut value)).map(|_| Parameter::Long(value))
+            },
+            'd' => {
+                if zval.is_float() {
+                    return Some(Parameter::Double(zval.value.double_value));
+                }
+                let mut value: f64 = 0.0;
+                parsed(zend_parse_arg_double_slow(argument, &mut value)).map(|_| Parameter::Double(value))
+            },
+            'b' => {
+                if zval.type_info.is_from_type(InternalPhpTypes::TRUE) {
+                    return Some(Parameter::Bool(true));
+                }
+                if zval.type_info.is_from_type(InternalPhpTypes::FALSE) {
+                    return Some(Parameter::Bool(false));
+                }
+                let mut value: u8 = 0;
+                parsed(zend_parse_arg_bool_slow(argument, &mut value)).map(|_| Parameter::Bool(value != 0))
+            },
+            's' => {
+                let mut string = zval.value.string;
+                if !zval.is_string() && zend_parse_arg_str_slow(argument, &mut string) == 0 {
+                    return None;
+                }
+                // The argument now holds the string, so it lives as long as the call
+                Some(Parameter::String(std::slice::from_raw_parts((*string).value.as_ptr(), (*string).len)))
+            },
+            'a' if zval.is_array() => Some(Parameter::Array(ZvalRef::from_ptr(argument))),
+            'h' => zval.as_array().map(Parameter::HashTable),
+            'o' if zval.is_object() => Some(Parameter::Object(ZvalRef::from_ptr(argument))),
+            'f' if zend_is_callable(argument, 0, ptr::null_mut()) != 0 => Some(Parameter::Callable(ZvalRef::from_ptr(argument))),
+            'z' => Some(Parameter::Zval(ZvalRef::from_ptr(argument))),
+            _ => None,
+        }
+    }
+}
+
+fn parsed(result: c_int) -> Option<()> {
+    if result == 0 {
+        return None;
+    }
+    Some(())
+}
+
+fn expected_type(kind: char) -> c_int {
+    match kind {
+        'l' => EXPECTED_LONG,
+        'd' => EXPECTED_DOUBLE,
+        'b' => EXPECTED_BOOL,
+        's' => EXPECTED_STRING,
+        'a' | 'h' => EXPECTED_ARRAY,
+        'f' => EXPECTED_FUNC,
+        _ => EXPECTED_OBJECT,
+    }
+}
+
+#[cfg(feature = "php73")]
+fn wrong_parameters_count(_passed: u32, min: u32, max: Option<u32>) {
+    unsafe {
+        zend_wrong_parameters_count_error(min as c_int, max.map_or(-1, |max| max as c_int));
+    }
+}
+
+#[cfg(not(feature = "php73"))]
+fn wrong_parameters_count(passed: u32, min: u32, max: Option<u32>) {
+    unsafe {
+        zend_wrong_parameters_count_error(0, passed as c_int, min as c_int, max.map_or(-1, |max| max as c_int));
+    }
+}
+
+#[cfg(feature = "php73")]
+fn wrong_parameter_type(position: u32, expected_type: c_int, argument: *mut Zval) {
+    unsafe {
+        zend_wrong_parameter_type_error(position as c_int, expected_type, argument);
+    }
+}
+
+#[cfg(not(feature = "php73"))]
+fn wrong_parameter_type(position: u32, expected_type: c_int, argument: *mut Zval) {
+    unsafe {
+        zend_wrong_parameter_type_error(0, position as c_int, expected_type, argument);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spec_counts_required_and_optional_parameters() {
+        let (items, min, max) = parse_spec("sl|z/a!");
+        let kinds: String = items.iter().map(|item| item.kind).collect();
+        assert_eq!(kinds, "slza");
+        assert_eq!((min, max), (2, Some(4)));
+        assert!(items[2].writable && !items[2].nullable);
+        assert!(items[3].nullable && !items[3].writable);
+    }
+
+    #[test]
+    fn spec_with_variadic_has_no_maximum() {
+        let (_, min, max) = parse_spec("s*");
+        assert_eq!((min, max), (1, None));
+        let (_, min, max) = parse_spec("s+");
+        assert_eq!((min, max), (2, None));
+    }
+
+    #[test]
+    #[should_panic(expected = "Variadic parameters must be the last ones")]
+    fn spec_rejects_parameters_after_variadic() {
+        parse_spec("*s");
+    }
+
+    #[test]
+    fn strings_must_be_utf8_unless_read_as_bytes() {
+        assert_eq!(String::from_parameter(Parameter::String(b"caf\xc3\xa9")).ok(), Some("café".to_string()));
+        assert!(matches!(String::from_parameter(Parameter::String(b"\xff")), Err(FromParameterError::InvalidUtf8)));
+        assert!(matches!(<&str>::from_parameter(Parameter::String(b"\xff")), Err(FromParameterError::InvalidUtf8)));
+        assert!(matches!(Cow::<str>::from_parameter(Parameter::String(b"\xff")), Err(FromParameterError::InvalidUtf8)));
+        assert_eq!(<&[u8]>::from_parameter(Parameter::String(b"\xff")).ok(), Some(&b"\xff"[..]));
+        assert_eq!(Vec::<u8>::from_parameter(Parameter::String(b"\xff")).ok(), Some(vec![0xff]));
+    }
+
+    #[test]
+    fn missing_parameters_are_none() {
+        assert_eq!(Option::<i64>::from_parameter(Parameter::Missing).ok(), Some(None));
+        assert!(matches!(i64::from_parameter(Parameter::Bool(true)), Err(FromParameterError::WrongKind(Parameter::Bool(true)))));
+    }
+}
@@ -0,0 +1,172 @@
+use std::{fmt, mem, slice, str};
+use std::fmt::{Debug, Formatter};
+use std::ops::Deref;
+use libc::*;
+
+use super::internal_php_methods::*;
+use super::types::*;
+
+/// A PHP string (`zend_string`). The bytes may contain NULs and don't need to be UTF-8.
+///
+/// A `PhpString` holds one reference to the string, cloning it adds one and dropping it releases it.
+/// Interned strings are shared by the engine and are never released.
+///
+/// Strings made with `new` are allocated for the current request and must not outlive it.
+/// Strings kept by the module, like names given to the engine, should be `persistent` or `interned`.
+#[repr(transparent)]
+pub struct PhpString {
+	string: *mut ZendString,
+}
+
+impl PhpString {
+	/// Creates a string with a copy of the bytes, freed at the end of the request
+	pub fn new(bytes: impl AsRef<[u8]>) -> Self {
+		PhpString { string: zend_string_init(bytes.as_ref(), false) }
+	}
+
+	/// Creates a string with a copy of the bytes that outlives the requests
+	pub fn persistent(bytes: impl AsRef<[u8]>) -> Self {
+		PhpString { string: zend_string_init(bytes.as_ref(), true) }
+	}
+
+	/// Creates an interned string, or returns the one already interned with the same bytes.
+	/// Interned while the module starts up, it lives as long as the module.
+	/// Interned during a request, it lives until the request ends.
+	pub fn interned(bytes: impl AsRef<[u8]>) -> Self {
+		PhpString { string: unsafe { intern_zend_string(zend_string_init(bytes.as_ref(), true)) } }
+	}
+
+	/// Borrows the string if it is valid UTF-8
+	pub fn as_str(&self) -> Option<&str> {
+		str::from_utf8(self).ok()
+	}
+
+	/// Returns if the string is interned
+	pub fn is_interned(&self) -> bool {
+		self.flags() & GC_IMMUTABLE != 0
+	}
+
+	/// Returns if the string outlives the requests
+	pub fn is_persistent(&self) -> bool {
+		self.flags() & IS_STR_PERSISTENT != 0
+	}
+
+	fn flags(&self) -> u32 {
+		unsafe { (*self.string).gc.type_info >> GC_FLAGS_SHIFT }
+	}
+
+	/// Gives the reference to a zval
+	pub fn into_zval(self) -> Zval {
+		let mut zval = Zval::new_as_null();
+		zval.value.string = self.string;
+		zval.type_info.type_info = if self.is_interned() { InternalPhpTypes::STRING as u32 } else { IS_STRING_EX };
+		mem::forget(self);
+		zval
+	}
+
+	/// Takes ownership of one reference to a string made by the engine
+	pub(crate) unsafe fn from_raw(string: *mut ZendString) -> Self {
+		PhpString { string }
+	}
+
+	/// Gives the reference to the caller, for the engine to keep it
+	pub(crate) fn into_raw(self) -> *mut ZendString {
+		let string = self.string;
+		mem::forget(self);
+		string
+	}
+}
+
+impl Deref for PhpString {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		unsafe { slice::from_raw_parts((*self.string).value.as_ptr(), (*self.string).len) }
+	}
+}
+
+impl AsRef<[u8]> for PhpString {
+	fn as_ref(&self) -> &[u8] {
+		self
+	}
+}
+
+impl Clone for PhpString {
+	fn clone(&self) -> Self {
+		if !self.is_interned() {
+			unsafe { (*self.string).gc.ref_count += 1 };
+		}
+		PhpString { string: self.string }
+	}
+}
+
+impl Drop for PhpString {
+	fn drop(&mut self) {
+		if self.is_interned() {
+			return;
+		}
+		unsafe {
+			(*self.string).gc.ref_count -= 1;
+			if (*self.string).gc.ref_count == 0 {
+				if self.is_persistent() {
+					free(self.string as *mut c_void);
+				} else {
+					_efree(self.string as *mut c_void);
+				}
+			}
+		}
+	}
+}
+
+impl PartialEq for PhpString {
+	fn eq(&self, other: &Self) -> bool {
+		**self == **other
+	}
+}
+
+impl Eq for PhpString {}
+
+impl Debug for PhpString {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		Debug::fmt(&String::from_utf8_lossy(self), f)
+	}
+}
+
+impl From<&str> for PhpString {
+	fn from(string: &str) -> Self {
+		PhpString::new(string)
+	}
+}
+
+impl From<&[u8]> for PhpString {
+	fn from(bytes: &[u8]) -> Self {
+		PhpString::new(bytes)
+	}
+}
+
+impl From<PhpString> for Zval {
+	fn from(string: PhpString) -> Self {
+		string.into_zval()
+	}
+}
+
+/// Takes a new reference to the string, without copying it
+impl FromPhpZval for PhpString {
+	fn try_from(zval: Zval) -> Result<Self, PhpTypeConversionError> {
+		let zval = zval.handle_indirect();
+		match zval.as_php_string() {
+			Some(string) => Ok(string.clone()),
+			None => Err(PhpTypeConversionError::NotString(zval.type_info)),
+		}
+	}
+}
+
+impl Zval {
+	/// Borrows the string stored in this zval
+	pub fn as_php_string(&self) -> Option<&PhpString> {
+		if !self.is_string() {
+			return None;
+		}
+		unsafe { Some(&*(&self.value.string as *const *mut ZendString as *const PhpString)) }
+	}
+}
@@ -1,17 +1,123 @@
 use super::internal_php_methods::*;
-use std::os::raw::c_void;
-use std::ptr::null;
-use std::ffi::{CString, CStr};
-use std::{slice, fmt};
-use std::fmt::{Debug, Formatter};
-use crate::zend::php_echo;
-
-pub struct ExecuteData {}
+use std::os::raw::{c_int, c_void};
+use std::ptr::{self, null};
+use std::{slice, fmt, mem};
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::{Deref, DerefMut};
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "indexmap")]
+use indexmap::IndexMap;
+use super::class::{ClassEntry, ObjectHandlers};
+use super::array::ArrayKey;
+
+/// Call frame of the function being executed (`zend_execute_data`), for 64 bits builds.
+/// It is always created by the engine, so it can only be borrowed from a handler.
+#[repr(C)]
+pub struct ExecuteData {
+	opline: *const c_void,
+	call: *mut ExecuteData,
+	return_value: *mut Zval,
+	func: *mut ZendFunctionCommon,
+	this: Zval,
+	prev_execute_data: *mut ExecuteData,
+	symbol_table: *mut ZendArray,
+	run_time_cache: *mut *mut c_void,
+	/// Removed in PHP 7.3, where literals are found relative to the opline
+	#[cfg(not(feature = "php73"))]
+	literals: *mut Zval,
+}
 pub struct ModuleDep {}
 
+/// Head shared by internal and user functions (`zend_function.common`)
+#[repr(C)]
+struct ZendFunctionCommon {
+	type_: u8,
+	arg_flags: [u8; 3],
+	fn_flags: u32,
+	function_name: *mut ZendString,
+	scope: *mut ClassEntry,
+	prototype: *mut ZendFunctionCommon,
+	num_args: u32,
+	required_num_args: u32,
+	arg_info: *mut c_void,
+}
+
+/// Number of zval slots used by the call frame before the arguments (ZEND_CALL_FRAME_SLOT)
+const CALL_FRAME_SLOT: usize = mem::size_of::<ExecuteData>().div_ceil(mem::size_of::<Zval>());
+
+impl ExecuteData {
+	/// Returns the number of arguments passed to the function
+	pub fn arguments_count(&self) -> u32 {
+		self.this.u2
+	}
+
+	/// Borrows the argument at `index`, starting from 0.
+	/// Returns `None` if less arguments were passed.
+	pub fn argument(&self, index: u32) -> Option<ZvalRef<'_>> {
+		if index >= self.arguments_count() {
+			return None;
+		}
+		unsafe { Some(ZvalRef::from_ptr(self.argument_pointer(index))) }
+	}
+
+	/// Borrows all arguments passed to the function, in order
+	pub fn arguments(&self) -> impl ExactSizeIterator<Item = ZvalRef<'_>> {
+		(0..self.arguments_count()).map(move |index| unsafe { ZvalRef::from_ptr(self.argument_pointer(index)) })
+	}
+
+	pub(crate) fn argument_pointer(&self, index: u32) -> *mut Zval {
+		unsafe {
+			(self as *const ExecuteData as *mut Zval).add(CALL_FRAME_SLOT + index as usize)
+		}
+	}
+
+	/// Borrows `$this`. Returns `None` if the function was not called on an object.
+	pub fn this(&self) -> Option<ZvalRef<'_>> {
+		if self.this.is_object() {
+			Some(ZvalRef { zval: &self.this })
+		} else {
+			None
+		}
+	}
+
+	/// Returns the name of the called function, without the class name for methods
+	pub fn function_name(&self) -> Option<String> {
+		unsafe {
+			if self.func.is_null() {
+				return None;
+			}
+			zend_string_to_string((*self.func).function_name)
+		}
+	}
+
+	/// Returns the class where the called method is declared, or `None` for functions
+	pub fn scope(&self) -> Option<&ClassEntry> {
+		unsafe {
+			if self.func.is_null() {
+				return None;
+			}
+			(*self.func).scope.as_ref()
+		}
+	}
+
+	/// Returns the name of the class where the called method is declared
+	pub fn scope_name(&self) -> Option<String> {
+		self.scope().and_then(|scope| unsafe { zend_string_to_string(scope.name) })
+	}
+}
+
+unsafe fn zend_string_to_string(string: *mut ZendString) -> Option<String> {
+	if string.is_null() {
+		return None;
+	}
+	let bytes = slice::from_raw_parts((*string).value.as_ptr(), (*string).len);
+	Some(String::from_utf8_lossy(bytes).into_owned())
+}
+
 // Zend Types and Zval
 //https://github.com/php/php-src/blob/d0754b86b1cb4774c4af64498641ddaaab745418/Zend/zend_types.h#L176-L233
 
+#[allow(clippy::upper_case_acronyms)]
 pub enum InternalPhpTypes {
 	UNDEF = 0,
 	NULL = 1,
@@ -21,25 +127,108 @@ pub enum InternalPhpTypes {
 	DOUBLE = 5,
 	STRING = 6,
 	ARRAY = 7,
+	OBJECT = 8,
+	RESOURCE = 9,
 	REFERENCE = 10,
+	/// Constant expression (IS_CONSTANT_AST). Before PHP 7.3, 11 was IS_CONSTANT.
+	#[cfg(feature = "php73")]
+	AST = 11,
+	#[cfg(not(feature = "php73"))]
+	AST = 12,
+	#[cfg(feature = "php73")]
 	INDIRECT = 13,
-}
+	#[cfg(not(feature = "php73"))]
+	INDIRECT = 15,
+}
+
+/// What a zval holds, as returned by `Zval::kind`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZvalKind {
+	/// No value, like a missing argument or an unset property
+	Undef,
+	Null,
+	Bool,
+	Integer,
+	Float,
+	String,
+	Array,
+	Object,
+	Resource,
+	/// A PHP reference (`&$value`) wrapping another zval
+	Reference,
+	/// A constant expression not evaluated yet, found in default values
+	ConstantExpression,
+	/// A pointer to another zval, used by symbol tables and properties
+	Indirect,
+	/// Any other type used only inside the engine
+	Internal(u8),
+}
+
+#[cfg(feature = "php73")]
+pub const GC_FLAGS_SHIFT: u32 = 0;
+#[cfg(not(feature = "php73"))]
+pub const GC_FLAGS_SHIFT: u32 = 8;
+
+#[cfg(feature = "php73")]
+pub const IS_STR_PERSISTENT: u32 = 1 << 7;
+#[cfg(not(feature = "php73"))]
+pub const IS_STR_PERSISTENT: u32 = 1 << 0;
+
+/// Interned strings and immutable arrays are never freed by the reference counting
+#[cfg(feature = "php73")]
+pub const GC_IMMUTABLE: u32 = 1 << 6;
+#[cfg(not(feature = "php73"))]
+pub const GC_IMMUTABLE: u32 = 1 << 1;
+
+/// Type flag of zvals holding a reference counted value (IS_TYPE_REFCOUNTED << Z_TYPE_FLAGS_SHIFT)
+#[cfg(feature = "php73")]
+pub const IS_TYPE_REFCOUNTED: u32 = (1 << 0) << 8;
+#[cfg(not(feature = "php73"))]
+pub const IS_TYPE_REFCOUNTED: u32 = (1 << 2) << 8;
+
+/// Type flag of zvals holding a value that may be part of a cycle (IS_TYPE_COLLECTABLE << Z_TYPE_FLAGS_SHIFT)
+#[cfg(feature = "php73")]
+const IS_TYPE_COLLECTABLE: u32 = (1 << 1) << 8;
+#[cfg(not(feature = "php73"))]
+const IS_TYPE_COLLECTABLE: u32 = (1 << 3) << 8;
+
+/// Type flag of zvals holding a value that is duplicated on write (IS_TYPE_COPYABLE << Z_TYPE_FLAGS_SHIFT)
+#[cfg(feature = "php73")]
+const IS_TYPE_COPYABLE: u32 = 0;
+#[cfg(not(feature = "php73"))]
+const IS_TYPE_COPYABLE: u32 = (1 << 4) << 8;
+
+/// Type info of a reference counted string zval (IS_STRING_EX)
+pub const IS_STRING_EX: u32 = InternalPhpTypes::STRING as u32 | IS_TYPE_REFCOUNTED | IS_TYPE_COPYABLE;
+/// Type info of a reference counted array zval (IS_ARRAY_EX)
+pub const IS_ARRAY_EX: u32 = InternalPhpTypes::ARRAY as u32 | IS_TYPE_REFCOUNTED | IS_TYPE_COLLECTABLE | IS_TYPE_COPYABLE;
+/// Type info of an object zval (IS_OBJECT_EX)
+pub const IS_OBJECT_EX: u32 = InternalPhpTypes::OBJECT as u32 | IS_TYPE_REFCOUNTED | IS_TYPE_COLLECTABLE;
+/// Type info of a reference zval (IS_REFERENCE_EX)
+pub const IS_REFERENCE_EX: u32 = InternalPhpTypes::REFERENCE as u32 | IS_TYPE_REFCOUNTED;
+
+/// Array flag set when it is a list stored without a hash
+pub const HASH_FLAG_PACKED: u32 = 1 << 2;
 
 #[derive(Copy, Clone)]
 #[repr(C)]
 pub union ZendValue {
-	pub long_value: i64,
-	pub double_value: f64,
-	pub string: *mut ZendString,
-	pub array: *mut ZendArray,
-	pub zval: *mut Zval,
-	pub void: *mut c_void,
+	pub(crate) long_value: i64,
+	pub(crate) double_value: f64,
+	pub(crate) string: *mut ZendString,
+	pub(crate) array: *mut ZendArray,
+	pub(crate) object: *mut ZendObject,
+	pub(crate) resource: *mut ZendResource,
+	pub(crate) reference: *mut ZendReference,
+	pub(crate) zval: *mut Zval,
+	pub(crate) counted: *mut ZendRefCounted,
+	pub(crate) void: *mut c_void,
 }
 
 #[derive(Copy, Clone)]
 #[repr(C)]
 pub union TypeInfoUnion {
-	pub type_info: u32,
+	pub(crate) type_info: u32,
 }
 
 #[repr(C)]
@@ -51,16 +240,43 @@ pub struct ZendRefCounted {
 #[repr(C)]
 pub struct ZendString {
 	pub gc: ZendRefCounted,
-	pub hash: u32,
+	pub hash: u64,
 	pub len: usize,
 	pub value: [u8; 0],
 }
 
+#[repr(C)]
+pub struct ZendObject {
+	pub gc: ZendRefCounted,
+	pub handle: u32,
+	pub ce: *mut ClassEntry,
+	pub handlers: *const ObjectHandlers,
+	pub properties: *mut ZendArray,
+	pub properties_table: [Zval; 1],
+}
+
+#[repr(C)]
+pub struct ZendResource {
+	pub gc: ZendRefCounted,
+	pub handle: c_int,
+	pub resource_type: c_int,
+	pub ptr: *mut c_void,
+}
+
+/// The box shared by the zvals of a PHP reference (`&$value`).
+/// PHP 7.4 adds the typed properties bound to the reference after the value. Up to 7.3 a reference
+/// has no type, so any value can be assigned to it.
+#[repr(C)]
+pub struct ZendReference {
+	pub gc: ZendRefCounted,
+	pub val: Zval,
+}
+
 #[repr(C)]
 pub struct Bucket {
-	value: Zval,
-	hash: u64,
-	key: *mut ZendString,
+	pub(crate) value: Zval,
+	pub(crate) hash: u64,
+	pub(crate) key: *mut ZendString,
 }
 
 #[repr(C)]
@@ -68,24 +284,18 @@ struct DtorFunc {void: *mut c_void}
 
 #[repr(C)]
 pub struct ZendArray {
-	gc: ZendRefCounted,
+	pub(crate) gc: ZendRefCounted,
+	pub(crate) flags: u32,
 	n_table_mask: u32,
-	array_data: *mut Bucket,
-	n_num_used: u32,
-	n_num_of_elements: u32,
+	pub(crate) array_data: *mut Bucket,
+	pub(crate) n_num_used: u32,
+	pub(crate) n_num_of_elements: u32,
 	n_table_size: u32,
 	n_internal_pointer: u32,
-	n_next_free_element: u64,
+	n_next_free_element: i64,
 	p_destructor: DtorFunc,
 }
 
-impl ZendString {
-	pub fn new_as_pointer(rust_str: &str) -> *mut ZendString {
-		let c_format = CString::new(rust_str).unwrap();
-		create_zend_string(rust_str.len(), c_format.as_bytes_with_nul().as_ptr() as *const i8)
-	}
-}
-
 impl ZendArray {
 	pub fn new_in_zval(zval: &mut Zval) {
 		create_zend_array(zval);
@@ -93,7 +303,7 @@ impl ZendArray {
 
 	pub fn add_value(array: &mut Zval, key: &mut Zval, value: &mut Zval) {
 		unsafe {
-			array_set_zval_key((*array).value.array, key, value);
+			array_set_zval_key(array.value.array, key, value);
 		}
 	}
 }
@@ -103,9 +313,9 @@ impl ZendArray {
 /// and this value can change.
 #[repr(C)]
 pub struct Zval {
-	pub value: ZendValue,
-	pub type_info: TypeInfoUnion,
-	pub u2: u32,
+	pub(crate) value: ZendValue,
+	pub(crate) type_info: TypeInfoUnion,
+	pub(crate) u2: u32,
 }
 
 impl Zval {
@@ -126,56 +336,274 @@ impl Zval {
 		}
 	}
 
+	/// Copies the zval behind a pointer, adding a reference to its value
+	///
+	/// # Safety
+	///
+	/// `zval` must point to an initialized zval.
+	pub unsafe fn from_raw(zval: *const Zval) -> Self {
+		(*zval).clone()
+	}
+
+	/// Returns what the zval holds, to match on it
+	pub fn kind(&self) -> ZvalKind {
+		let type_byte = unsafe { self.type_info.type_info & 0xFF } as u8;
+		match type_byte {
+			0 => ZvalKind::Undef,
+			1 => ZvalKind::Null,
+			2 | 3 => ZvalKind::Bool,
+			4 => ZvalKind::Integer,
+			5 => ZvalKind::Float,
+			6 => ZvalKind::String,
+			7 => ZvalKind::Array,
+			8 => ZvalKind::Object,
+			9 => ZvalKind::Resource,
+			10 => ZvalKind::Reference,
+			#[cfg(not(feature = "php73"))]
+			11 => ZvalKind::ConstantExpression,
+			_ if type_byte == InternalPhpTypes::AST as u8 => ZvalKind::ConstantExpression,
+			_ if type_byte == InternalPhpTypes::INDIRECT as u8 => ZvalKind::Indirect,
+			_ => ZvalKind::Internal(type_byte),
+		}
+	}
+
 	/// Returns if a zval is undefined.
 	/// Undefined means that this zval holds no value
-	pub fn is_undef(self: &Self) -> bool {
+	pub fn is_undef(&self) -> bool {
 		self.type_info.is_from_type(InternalPhpTypes::UNDEF)
 	}
 
 	/// Returns if a zval is null
-	pub fn is_null(self: &Self) -> bool {
+	pub fn is_null(&self) -> bool {
 		self.type_info.is_from_type(InternalPhpTypes::NULL)
 	}
 
 	/// Returns if a zval is a integer (i64)
-	pub fn is_integer(self: &Self) -> bool { self.type_info.is_from_type(InternalPhpTypes::LONG) }
+	pub fn is_integer(&self) -> bool { self.type_info.is_from_type(InternalPhpTypes::LONG) }
 
 	/// Returns if a zval is a float (f64)
-	pub fn is_float(self: &Self) -> bool { self.type_info.is_from_type(InternalPhpTypes::DOUBLE) }
+	pub fn is_float(&self) -> bool { self.type_info.is_from_type(InternalPhpTypes::DOUBLE) }
 
 	/// Returns if a zval is string
-	pub fn is_string(self: &Self) -> bool {
+	pub fn is_string(&self) -> bool {
 		self.type_info.is_from_type(InternalPhpTypes::STRING)
 	}
 
 	/// Returns if a zval is array (Vec<>)
-	pub fn is_array(self: &Self) -> bool { self.type_info.is_from_type(InternalPhpTypes::ARRAY) }
+	pub fn is_array(&self) -> bool { self.type_info.is_from_type(InternalPhpTypes::ARRAY) }
+
+	/// Returns if a zval is an object
+	pub fn is_object(&self) -> bool { self.type_info.is_from_type(InternalPhpTypes::OBJECT) }
+
+	/// Returns if a zval is a resource
+	pub fn is_resource(&self) -> bool { self.type_info.is_from_type(InternalPhpTypes::RESOURCE) }
+
+	/// Returns if a zval is a PHP reference, shared with other variables
+	pub fn is_reference(&self) -> bool { self.type_info.is_from_type(InternalPhpTypes::REFERENCE) }
+
+	/// Returns the integer held by the zval, or `None` if it is not an integer
+	pub fn as_integer(&self) -> Option<i64> {
+		if self.is_integer() { Some(unsafe { self.value.long_value }) } else { None }
+	}
+
+	/// Returns the float held by the zval, or `None` if it is not a float
+	pub fn as_float(&self) -> Option<f64> {
+		if self.is_float() { Some(unsafe { self.value.double_value }) } else { None }
+	}
+
+	/// Returns the bool held by the zval, or `None` if it is not `true` or `false`
+	pub fn as_bool(&self) -> Option<bool> {
+		match self.kind() {
+			ZvalKind::Bool => Some(self.type_info.is_from_type(InternalPhpTypes::TRUE)),
+			_ => None,
+		}
+	}
+
+	/// Returns the zval holding the actual value, following references and indirect zvals
+	pub fn dereference(&self) -> &Zval {
+		let mut zval = self;
+		loop {
+			zval = unsafe {
+				match zval.kind() {
+					ZvalKind::Indirect => &*zval.value.zval,
+					ZvalKind::Reference => &(*zval.value.reference).val,
+					_ => return zval,
+				}
+			};
+		}
+	}
+
+	/// Returns the zval holding the actual value to change it. Changing it changes every variable
+	/// sharing the reference.
+	pub fn dereference_mut(&mut self) -> &mut Zval {
+		let mut zval = self;
+		loop {
+			zval = unsafe {
+				match zval.kind() {
+					ZvalKind::Indirect => &mut *zval.value.zval,
+					ZvalKind::Reference => &mut (*zval.value.reference).val,
+					_ => return zval,
+				}
+			};
+		}
+	}
+
+	/// Creates a PHP reference holding the value
+	pub fn new_reference<T>(value: T) -> Self
+		where Zval: From<T>
+	{
+		let mut zval = Zval::from(value);
+		zval.make_reference();
+		zval
+	}
+
+	/// Turns the zval into a reference holding its value, like `ZVAL_MAKE_REF`.
+	/// Clones of it then share the value, like variables assigned with `=&`.
+	pub fn make_reference(&mut self) {
+		if self.is_reference() {
+			return;
+		}
+		unsafe {
+			let reference = _emalloc(mem::size_of::<ZendReference>()) as *mut ZendReference;
+			ptr::write(reference, ZendReference {
+				gc: ZendRefCounted {ref_count: 1, type_info: InternalPhpTypes::REFERENCE as u32},
+				val: mem::replace(self, Zval::new_as_null()),
+			});
+			self.value.reference = reference;
+			self.type_info.type_info = IS_REFERENCE_EX;
+		}
+	}
 
 	/// Returns if a zval is indirect. Indirect is an internal type.
-	fn is_indirect(self: &Self) -> bool { self.type_info.is_from_type(InternalPhpTypes::INDIRECT) || self.type_info.is_from_type(InternalPhpTypes::REFERENCE) }
+	fn is_indirect(&self) -> bool { self.type_info.is_from_type(InternalPhpTypes::INDIRECT) || self.type_info.is_from_type(InternalPhpTypes::REFERENCE) }
 
-	fn handle_indirect(self) -> Zval {
+	/// Borrows the bytes of the string without copying them. Returns `None` if it is not a string.
+	pub fn as_bytes(&self) -> Option<&[u8]> {
+		if !self.is_string() {
+			return None;
+		}
+		unsafe { Some(slice::from_raw_parts((*self.value.string).value.as_ptr(), (*self.value.string).len)) }
+	}
+
+	/// Borrows the string without copying it.
+	/// Returns `None` if it is not a string or if it is not valid UTF-8.
+	pub fn as_str(&self) -> Option<&str> {
+		self.as_bytes().and_then(|bytes| std::str::from_utf8(bytes).ok())
+	}
+
+	/// Returns if the value is reference counted, like non interned strings, arrays and objects
+	pub fn is_refcounted(&self) -> bool {
+		unsafe { self.type_info.type_info & IS_TYPE_REFCOUNTED != 0 }
+	}
+
+	fn add_ref(&self) {
+		if self.is_refcounted() {
+			unsafe { (*self.value.counted).ref_count += 1 };
+		}
+	}
+
+	pub(crate) fn handle_indirect(self) -> Zval {
 		if self.is_indirect() {
-			return unsafe{Zval::from(self.value.zval)};
+			return self.dereference().clone();
 		}
 		self
 	}
 }
 
+/// A zval owned by the engine or by an array, borrowed for `'a`.
+/// It reads like a `Zval`, but it never releases the value. Use `to_zval` to keep the value.
+#[derive(Clone, Copy)]
+pub struct ZvalRef<'a> {
+	zval: &'a Zval,
+}
+
+impl<'a> ZvalRef<'a> {
+	pub(crate) unsafe fn from_ptr(zval: *const Zval) -> Self {
+		ZvalRef { zval: &*zval }
+	}
+
+	/// Borrows the bytes of the string for as long as the zval is borrowed
+	pub fn as_bytes(&self) -> Option<&'a [u8]> {
+		self.zval.as_bytes()
+	}
+
+	/// Borrows the string for as long as the zval is borrowed. Returns `None` if it is not valid UTF-8.
+	pub fn as_str(&self) -> Option<&'a str> {
+		self.zval.as_str()
+	}
+
+	/// Borrows the zval holding the actual value, following references
+	pub fn dereference(&self) -> ZvalRef<'a> {
+		ZvalRef { zval: self.zval.dereference() }
+	}
+
+	/// Copies the zval, adding a reference to its value
+	pub fn to_zval(&self) -> Zval {
+		self.zval.clone()
+	}
+}
+
+impl Deref for ZvalRef<'_> {
+	type Target = Zval;
+
+	fn deref(&self) -> &Zval {
+		self.zval
+	}
+}
+
+/// A zval owned by the engine or by an array, borrowed mutably for `'a`.
+/// Assigning to it releases the previous value and gives the new one to its owner.
+pub struct ZvalMut<'a> {
+	zval: &'a mut Zval,
+}
+
+impl<'a> ZvalMut<'a> {
+	pub(crate) unsafe fn from_ptr(zval: *mut Zval) -> Self {
+		ZvalMut { zval: &mut *zval }
+	}
+
+	/// Replaces the value. If the zval is a PHP reference, the value is replaced for every variable
+	/// sharing it, like an assignment in PHP.
+	pub fn set<T>(&mut self, value: T)
+		where Zval: From<T>
+	{
+		*self.zval.dereference_mut() = Zval::from(value);
+	}
+
+	/// Copies the zval, adding a reference to its value
+	pub fn to_zval(&self) -> Zval {
+		self.zval.clone()
+	}
+}
+
+impl Deref for ZvalMut<'_> {
+	type Target = Zval;
+
+	fn deref(&self) -> &Zval {
+		self.zval
+	}
+}
+
+impl DerefMut for ZvalMut<'_> {
+	fn deref_mut(&mut self) -> &mut Zval {
+		self.zval
+	}
+}
+
 /// Returns a value from you function back to PHP.
 /// You need to pass the retval from the function parameter and the value that you want to return.
 ///
-/// ```
+/// ```no_run
+/// # use solder::*;
 /// use solder::zend::{ExecuteData, Zval};
-/// #[no_mangle]
-/// pub extern fn hello_world(_data: &ExecuteData, retval: &mut Zval) {
+/// pub fn hello_world(_data: &ExecuteData, retval: &mut Zval) {
 ///    php_return!(retval, "Hello World!");
 ///}
 /// ```
 #[macro_export]
 macro_rules! php_return {
     ($retval:expr, $value:expr) => {
-        (*$retval) = Zval::new($value);
+        (*$retval) = $crate::zend::Zval::new($value);
         return;
     };
 }
@@ -183,8 +611,8 @@ macro_rules! php_return {
 impl From<&str> for Zval {
 	fn from(rust_str: &str) -> Self {
 		Zval {
-			value: ZendValue{string: ZendString::new_as_pointer(rust_str)},
-			type_info: TypeInfoUnion {type_info: InternalPhpTypes::STRING as u32},
+			value: ZendValue{string: zend_string_init(rust_str.as_bytes(), false)},
+			type_info: TypeInfoUnion {type_info: IS_STRING_EX},
 			u2: 0,
 		}
 	}
@@ -196,6 +624,24 @@ impl From<String> for Zval {
 	}
 }
 
+/// Creates a binary safe string
+impl From<&[u8]> for Zval {
+	fn from(bytes: &[u8]) -> Self {
+		Zval {
+			value: ZendValue{string: zend_string_init(bytes, false)},
+			type_info: TypeInfoUnion {type_info: IS_STRING_EX},
+			u2: 0,
+		}
+	}
+}
+
+/// Creates a binary safe string
+impl From<Vec<u8>> for Zval {
+	fn from(bytes: Vec<u8>) -> Self {
+		Zval::from(bytes.as_slice())
+	}
+}
+
 impl From<i64> for Zval {
 	fn from(number: i64) -> Self {
 		Zval {
@@ -246,6 +692,28 @@ impl From<f64> for Zval {
 	}
 }
 
+impl From<bool> for Zval {
+	fn from(value: bool) -> Self {
+		Zval {
+			value: ZendValue{long_value: 0},
+			type_info: TypeInfoUnion {type_info: if value { InternalPhpTypes::TRUE } else { InternalPhpTypes::FALSE } as u32},
+			u2: 0,
+		}
+	}
+}
+
+/// `None` is null
+impl<T> From<Option<T>> for Zval
+	where Zval: From<T>
+{
+	fn from(value: Option<T>) -> Self {
+		match value {
+			Some(value) => Zval::from(value),
+			None => Zval::new_as_null(),
+		}
+	}
+}
+
 impl<T: Clone> From<Vec<T>> for Zval
 	where Zval: From<T>
 {
@@ -263,53 +731,42 @@ impl<T: Clone> From<Vec<T>> for Zval
 	}
 }
 
-impl From<*mut ZendString> for Zval {
-	fn from(string: *mut ZendString) -> Self {
+/// Takes ownership of one reference to the object
+impl From<*mut ZendObject> for Zval {
+	fn from(object: *mut ZendObject) -> Self {
 		Zval {
-			value: ZendValue{string},
-			type_info: TypeInfoUnion{type_info: InternalPhpTypes::STRING as u32},
+			value: ZendValue{object},
+			type_info: TypeInfoUnion{type_info: IS_OBJECT_EX},
 			u2: 0,
 		}
 	}
 }
 
-impl From<*mut Zval> for Zval {
-	fn from(zval: *mut Zval) -> Self {
-		unsafe {
-			Zval {
-				value: (*zval).value,
-				type_info: (*zval).type_info,
-				u2: (*zval).u2,
-			}
-		}
-	}
-}
 
-/// This clone is not safe because we are copying the pointers and not the values.
-/// The PHP GC will (probably) not deallocate this parameters as long as we stay single threaded.
-/// But, I do need to improve this implementation
+/// Cloning shares the value, adding a reference to it like PHP does when assigning variables
 impl Clone for Zval {
 	fn clone(&self) -> Self {
+		self.add_ref();
 		Zval {
-			value: ZendValue{long_value: unsafe{self.value.long_value}},
+			value: self.value,
 			type_info: TypeInfoUnion {type_info: unsafe{self.type_info.type_info}},
 			u2: self.u2
 		}
 	}
 }
 
-/// We still need to handle arrays and garbage collected Zvals
+/// Releases one reference to the value, destroying it when it was the last one
 impl Drop for Zval {
 	fn drop(&mut self) {
-		if self.is_string() {
-			free_zend_string(unsafe{self.value.string});
+		if self.is_refcounted() {
+			unsafe { release_zval(self) };
 		}
 	}
 }
 
 impl TypeInfoUnion {
-	fn is_from_type(self: &Self, php_type: InternalPhpTypes) -> bool {
-		unsafe {self.type_info & 0x000F == php_type as u32}
+	pub(crate) fn is_from_type(&self, php_type: InternalPhpTypes) -> bool {
+		unsafe {self.type_info & 0x00FF == php_type as u32}
 	}
 }
 
@@ -320,9 +777,12 @@ pub enum PhpTypeConversionError {
 	NotFloat(TypeInfoUnion),
 	NotString(TypeInfoUnion),
 	NotArray(TypeInfoUnion),
+	/// A string was read as a Rust `String` but is not valid UTF-8.
+	/// Binary strings can be read as `Vec<u8>` instead.
+	InvalidUtf8,
 }
 
-impl Debug for PhpTypeConversionError {
+impl Display for PhpTypeConversionError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             PhpTypeConversionError::NotBool(type_info) => write!(f, "Not a bool. Type info is {}", unsafe{type_info.type_info}),
@@ -330,10 +790,17 @@ impl Debug for PhpTypeConversionError {
             PhpTypeConversionError::NotFloat(type_info) => write!(f, "Not a float. Type info is {}", unsafe{type_info.type_info}),
             PhpTypeConversionError::NotString(type_info) => write!(f, "Not a string. Type info is {}", unsafe{type_info.type_info}),
             PhpTypeConversionError::NotArray(type_info) => write!(f, "Not a array. Type info is {}", unsafe{type_info.type_info}),
+            PhpTypeConversionError::InvalidUtf8 => write!(f, "Not a valid UTF-8 string"),
         }
     }
 }
 
+impl Debug for PhpTypeConversionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
 pub trait FromPhpZval: Sized {
 	fn try_from(value: Zval) -> Result<Self, PhpTypeConversionError>;
 }
@@ -377,17 +844,21 @@ impl FromPhpZval for String {
 		if !zval.is_string() {
 			return Err(PhpTypeConversionError::NotString(zval.type_info));
 		}
-		let c_str = unsafe {
-			 CStr::from_bytes_with_nul_unchecked(
-				slice::from_raw_parts((*zval.value.string).value.as_ptr(), (*zval.value.string).len as usize + 1)
-			)
-		};
-		return match c_str.to_str() {
-			Ok(str) => Ok(str.to_string()),
-			//Not a very good error.
-			Err(_) => Err(PhpTypeConversionError::NotString(TypeInfoUnion{type_info: 666})),
-		};
+		match zval.as_str() {
+			Some(str) => Ok(str.to_string()),
+			None => Err(PhpTypeConversionError::InvalidUtf8),
+		}
+	}
+}
 
+/// Reads the bytes of a string, which don't need to be UTF-8
+impl FromPhpZval for Vec<u8> {
+	fn try_from(zval: Zval) -> Result<Self, PhpTypeConversionError> {
+		let zval = zval.handle_indirect();
+		match zval.as_bytes() {
+			Some(bytes) => Ok(bytes.to_vec()),
+			None => Err(PhpTypeConversionError::NotString(zval.type_info)),
+		}
 	}
 }
 
@@ -409,29 +880,106 @@ impl <T: FromPhpZval> FromPhpZval for Vec<T> {
 	}
 }
 
-pub fn free_zend_string(zend_string: *mut ZendString) {
-	let ref_counted = unsafe{&(*zend_string).gc};
-	if !check_gc_flags(ref_counted, 6) {
-		if should_free(unsafe{&mut (*zend_string).gc}) {
-			if check_gc_flags(ref_counted, 7) {
-				unsafe{free(zend_string as *mut c_void)}
-				return;
-			} else {
-				unsafe{_efree(zend_string as *mut c_void)}
-				return;
-			};
-		}
+impl <T: FromPhpZval> FromPhpZval for HashMap<String, T> {
+	fn try_from(zval: Zval) -> Result<Self, PhpTypeConversionError> {
+		array_entries(zval).map(|entries| entries.into_iter().collect())
 	}
 }
 
-fn check_gc_flags(ref_counted: &ZendRefCounted, position: u32) -> bool {
-	((ref_counted.type_info & 0x000003f0) >> 3) & (1 << position) != 0
+impl <T: FromPhpZval> FromPhpZval for BTreeMap<String, T> {
+	fn try_from(zval: Zval) -> Result<Self, PhpTypeConversionError> {
+		array_entries(zval).map(|entries| entries.into_iter().collect())
+	}
 }
 
-fn should_free(ref_counted: &mut ZendRefCounted) -> bool {
-	if ref_counted.ref_count == 0 {
-		return true;
+/// Keeps the order of the PHP array
+#[cfg(feature = "indexmap")]
+impl <T: FromPhpZval> FromPhpZval for IndexMap<String, T> {
+	fn try_from(zval: Zval) -> Result<Self, PhpTypeConversionError> {
+		array_entries(zval).map(|entries| entries.into_iter().collect())
+	}
+}
+
+/// Reads the entries of an array in order. Integer keys are converted to strings.
+fn array_entries<T: FromPhpZval>(zval: Zval) -> Result<Vec<(String, T)>, PhpTypeConversionError> {
+	let zval = zval.handle_indirect();
+	let array = match zval.as_array() {
+		Some(array) => array,
+		None => return Err(PhpTypeConversionError::NotArray(zval.type_info)),
+	};
+	let mut returner = Vec::with_capacity(array.len());
+	for (key, value) in array {
+		let key = match key {
+			ArrayKey::Int(index) => index.to_string(),
+			ArrayKey::Str(bytes) => match std::str::from_utf8(bytes) {
+				Ok(key) => key.to_string(),
+				Err(_) => return Err(PhpTypeConversionError::InvalidUtf8),
+			},
+		};
+		returner.push((key, T::try_from(value.to_zval())?));
+	}
+	Ok(returner)
+}
+
+/// Creates an array from key and value pairs. Numeric string keys become integer keys, like in PHP.
+fn new_array<T>(entries: impl Iterator<Item = (String, T)>) -> Zval
+	where Zval: From<T>
+{
+	let mut returner = Zval::new_as_null();
+	ZendArray::new_in_zval(&mut returner);
+	for (key, value) in entries {
+		ZendArray::add_value(&mut returner, &mut Zval::new::<String>(key), &mut Zval::from(value));
+	}
+	returner
+}
+
+impl<T> From<HashMap<String, T>> for Zval
+	where Zval: From<T>
+{
+	fn from(map: HashMap<String, T>) -> Self {
+		new_array(map.into_iter())
+	}
+}
+
+impl<T> From<BTreeMap<String, T>> for Zval
+	where Zval: From<T>
+{
+	fn from(map: BTreeMap<String, T>) -> Self {
+		new_array(map.into_iter())
+	}
+}
+
+#[cfg(feature = "indexmap")]
+impl<T> From<IndexMap<String, T>> for Zval
+	where Zval: From<T>
+{
+	fn from(map: IndexMap<String, T>) -> Self {
+		new_array(map.into_iter())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn call_frame_matches_the_engine_layout() {
+		#[cfg(feature = "php73")]
+		assert_eq!(mem::size_of::<ExecuteData>(), 72);
+		#[cfg(not(feature = "php73"))]
+		assert_eq!(mem::size_of::<ExecuteData>(), 80);
+		assert_eq!(CALL_FRAME_SLOT, 5);
+	}
+
+	#[test]
+	fn scalars_are_read_by_their_type() {
+		// Scalars hold no reference, so the zvals can be forgotten instead of released by PHP
+		let integer = mem::ManuallyDrop::new(Zval::from(7i64));
+		let float = mem::ManuallyDrop::new(Zval::from(1.5));
+		let boolean = mem::ManuallyDrop::new(Zval::from(false));
+		assert_eq!((integer.as_integer(), integer.as_float(), integer.as_bool()), (Some(7), None, None));
+		assert_eq!((float.as_integer(), float.as_float()), (None, Some(1.5)));
+		assert_eq!((boolean.as_bool(), boolean.as_integer()), (Some(false), None));
+		assert_eq!(mem::ManuallyDrop::new(Zval::new_as_null()).as_bool(), None);
 	}
-	ref_counted.ref_count -= 1;
-	return ref_counted.ref_count <= 0;
 }
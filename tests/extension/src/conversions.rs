@@ -0,0 +1,13 @@
+//! Functions declared outside of the `#[php_module]`
+
+use solder::*;
+
+#[php_function]
+fn join_words(words: Vec<String>) -> String {
+    words.join(" ")
+}
+
+#[php_function]
+fn list_entries(values: std::collections::BTreeMap<String, i64>) -> String {
+    values.iter().map(|(key, value)| format!("{}={}", key, value)).collect::<Vec<_>>().join(",")
+}
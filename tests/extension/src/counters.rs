@@ -0,0 +1,39 @@
+//! Classes declared outside of the `#[php_module]`, on a struct and by functions
+
+use solder::*;
+use solder::zend::*;
+
+/// Declared before its parent, which the module registers first
+#[php_class]
+fn limited_counter() -> Class {
+    ClassBuilder::new(c_str!("SolderTests\\LimitedCounter"))
+        .with_parent(c_str!("SolderTests\\Counter"))
+        .build()
+}
+
+#[php_class(name = "SolderTests\\Counter")]
+#[derive(Default, Clone)]
+pub struct Counter {
+    count: i64,
+}
+
+impl PhpClass for Counter {
+    fn build(class: ClassBuilder) -> ClassBuilder {
+        class.with_method(FunctionBuilder::new(c_str!("increment"), counter_increment).build())
+    }
+}
+
+fn counter_increment(data: &ExecuteData, retval: &mut Zval) {
+    let mut counter = data.this_object::<Counter>().unwrap();
+    counter.count += 1;
+    php_return!(retval, counter.count);
+}
+
+#[php_class]
+fn cloneable_counter() -> Class {
+    ClassBuilder::new(c_str!("SolderTests\\CloneableCounter"))
+        .with_cloneable_state::<Counter>()
+        .with_property(c_str!("label"), "counter", ACC_PUBLIC)
+        .with_method(FunctionBuilder::new(c_str!("increment"), counter_increment).build())
+        .build()
+}
@@ -0,0 +1,32 @@
+//! Module globals, given to the module by its `#[php_builder]`
+
+use solder::*;
+use solder::zend::*;
+
+pub struct Globals {
+    calls: i64,
+    names: Vec<String>,
+}
+
+impl Default for Globals {
+    fn default() -> Self {
+        if std::env::var_os("SOLDER_TESTS_GLOBALS_PANIC").is_some() {
+            panic!("Globals failed");
+        }
+        Globals { calls: 0, names: Vec::new() }
+    }
+}
+
+#[php_function]
+fn count_call(name: String) -> i64 {
+    module_globals(|globals: &mut Globals| {
+        globals.calls += 1;
+        globals.names.push(name);
+        globals.calls
+    })
+}
+
+#[php_function]
+fn called_names() -> Vec<String> {
+    module_globals(|globals: &mut Globals| globals.names.clone())
+}
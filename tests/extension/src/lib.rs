@@ -0,0 +1,220 @@
+//! Extension loaded by the PHP scripts of `tests/php`. Each script exercises a part of solder
+//! through the functions and classes declared here.
+
+use solder::*;
+
+mod conversions;
+mod counters;
+mod globals;
+
+#[php_module(name = "solder_tests", version = "0.1.0")]
+mod solder_tests {
+    use solder::*;
+    use solder::zend::*;
+    use solder::info::*;
+
+    #[php_startup]
+    fn startup() {
+        if std::env::var_os("SOLDER_TESTS_STARTUP_PANIC").is_some() {
+            panic!("Startup failed");
+        }
+    }
+
+    #[php_builder]
+    fn builder(builder: ModuleBuilder) -> ModuleBuilder {
+        builder
+            .with_globals::<crate::globals::Globals>()
+            .with_ini_entry(IniEntryBuilder::new(c_str!("solder_tests.greeting"), c_str!("Hello")).build())
+            .with_ini_entry(IniEntryBuilder::new(c_str!("solder_tests.limit"), c_str!("128M"))
+                .with_type(IniType::Integer)
+                .build())
+            .with_ini_entry(IniEntryBuilder::new(c_str!("solder_tests.enabled"), c_str!("On"))
+                .with_type(IniType::Bool)
+                .build())
+            .with_ini_entry(IniEntryBuilder::new(c_str!("solder_tests.mode"), c_str!("fast"))
+                .with_on_modify(|value| match value {
+                    "fast" | "safe" => true,
+                    "panic" => panic!("Invalid mode"),
+                    _ => false,
+                })
+                .build())
+            .with_constant(c_str!("SOLDER_TESTS_VERSION"), "0.1.0")
+            .with_constant(c_str!("SOLDER_TESTS_LIMIT"), 128)
+            .with_constant(c_str!("SOLDER_TESTS_RATIO"), 0.5)
+            .with_constant(c_str!("SOLDER_TESTS_ENABLED"), true)
+            .with_constant(c_str!("SOLDER_TESTS_NOTHING"), ())
+            .with_constant_entry(ConstantBuilder::new(c_str!("MODE_FAST"), 1)
+                .with_namespace(c_str!("SolderTests"))
+                .build())
+            .with_constant_entry(ConstantBuilder::new(c_str!("DEFAULT_MODE"), "fast")
+                .with_namespace(c_str!("SolderTests\\"))
+                .build())
+    }
+
+    #[php_info]
+    fn info() {
+        print_table_start();
+        print_table_row("Rust info", "enabled");
+        print_table_end();
+        InfoTable::new()
+            .with_header(&["Rust cache", "Hits", "Misses"])
+            .with_row(&["Rust users", "10", "2"])
+            .with_section("Connections")
+            .with_row(&["Rust primary", "up", ""])
+            .with_row(&["Rust wide", "1", "2", "", "4", "5", "6", "7", "8"])
+            .print();
+        print_box_start(false);
+        print_box_text("Rust box <text>");
+        print_box_end();
+        if std::env::var_os("SOLDER_TESTS_INFO_PANIC").is_some() {
+            panic!("Info failed");
+        }
+    }
+
+    #[php_function]
+    fn describe_arguments(data: &ExecuteData, _arguments: Vec<Zval>) -> String {
+        let strings = data.arguments().filter(|argument| argument.is_string()).count();
+        format!("{}() got {} arguments, {} strings", data.function_name().unwrap_or_default(), data.arguments().len(), strings)
+    }
+
+    #[php_function]
+    fn greet(name: String, greeting: Option<&str>) -> String {
+        format!("{} {}", greeting.unwrap_or("Hello"), name)
+    }
+
+    #[php_function]
+    fn byte_length(bytes: &[u8]) -> i64 {
+        bytes.len() as i64
+    }
+
+    #[php_function]
+    fn shared_string(text: PhpString) -> Vec<PhpString> {
+        let copy = text.clone();
+        vec![text, copy, PhpString::new(b"\xff\0end")]
+    }
+
+    fn repeat(data: &ExecuteData, retval: &mut Zval) {
+        php_parse_parameters!(data, "sl|s", text: String, times: i64, separator: Option<String>);
+        let separator = separator.unwrap_or_default();
+        php_return!(retval, vec![text; times as usize].join(&separator));
+    }
+
+    #[php_function]
+    fn set_reference(mut variable: ZvalMut, value: i64) {
+        variable.set(value);
+    }
+
+    /// Changes its array argument, which is passed by value
+    fn append_local(data: &ExecuteData, retval: &mut Zval) {
+        php_parse_parameters!(data, "z/", values: ZvalMut);
+        let mut values = values;
+        let array = values.as_array_mut().unwrap();
+        array.push("appended");
+        php_return!(retval, array.len());
+    }
+
+    #[php_function]
+    fn with_entry(values: Zval, key: String) -> Zval {
+        let mut values = values;
+        if let Some(array) = values.as_array_mut() {
+            let mut entry = PhpArray::new();
+            entry.push(key.as_str());
+            array.insert(key.as_str(), entry);
+        }
+        values
+    }
+
+    /// Throws a builtin exception class found by name
+    fn check_name(data: &ExecuteData, _retval: &mut Zval) {
+        php_parse_parameters!(data, "s", name: String);
+        if name.is_empty() {
+            let class = find_class("InvalidArgumentException").unwrap();
+            unsafe { throw_exception(class, "Name can't be empty", 7) };
+        }
+    }
+
+    /// Reads its array argument with the `h` spec
+    fn count_entries(data: &ExecuteData, retval: &mut Zval) {
+        php_parse_parameters!(data, "h", values: &PhpArray);
+        php_return!(retval, values.len());
+    }
+
+    /// Asks an integer for a string parameter
+    fn mismatched_spec(data: &ExecuteData, _retval: &mut Zval) {
+        php_parse_parameters!(data, "s", _number: i64);
+    }
+
+    #[php_class]
+    fn parse_error() -> Class {
+        ExceptionBuilder::new(c_str!("SolderTests\\ParseError"))
+            .with_parent(c_str!("RuntimeException"))
+            .with_property(c_str!("input"), "")
+            .build()
+    }
+
+    #[php_function]
+    fn parse_number(text: &str) -> Result<i64, PhpException> {
+        text.trim().parse().map_err(|_| {
+            PhpException::new("SolderTests\\ParseError", "Not a number")
+                .with_code(3)
+                .with_property("input", text)
+                .with_previous(PhpException::new("LogicException", "Expected digits"))
+        })
+    }
+
+    pub enum SettingError {
+        Missing(String),
+        Invalid(String),
+    }
+
+    impl IntoPhpException for SettingError {
+        fn into_php_exception(self) -> PhpException {
+            match self {
+                SettingError::Missing(name) => PhpException::new("OutOfBoundsException", &format!("Missing {}", name)),
+                SettingError::Invalid(name) => PhpException::new("UnexpectedValueException", &format!("Invalid {}", name)).with_code(2),
+            }
+        }
+    }
+
+    #[php_function]
+    fn read_setting(settings: std::collections::BTreeMap<String, String>, name: String) -> Result<i64, SettingError> {
+        let value = settings.get(&name).ok_or_else(|| SettingError::Missing(name.clone()))?;
+        value.parse().map_err(|_| SettingError::Invalid(name))
+    }
+
+    #[php_function]
+    fn fail_with_message(message: String) -> Result<(), String> {
+        Err(message)
+    }
+
+    #[php_function]
+    fn kinds(values: Vec<ZvalRef>) -> Vec<String> {
+        values.iter().map(|value| format!("{:?}", value.kind())).collect()
+    }
+
+    /// Reads an INI entry with the getter of a type
+    #[php_function]
+    fn read_ini(name: &str, kind: &str) -> Zval {
+        match kind {
+            "integer" => ini_integer(name).into(),
+            "float" => ini_float(name).into(),
+            "bool" => ini_bool(name).into(),
+            _ => ini_string(name).into(),
+        }
+    }
+
+    /// Handlers written without `#[php_function]`, as static methods
+    #[php_class]
+    fn handlers() -> Class {
+        ClassBuilder::new(c_str!("SolderTests\\Handlers"))
+            .with_method(FunctionBuilder::new(c_str!("repeat"), repeat).with_flags(ACC_STATIC).build())
+            .with_method(FunctionBuilder::new(c_str!("checkName"), check_name).with_flags(ACC_STATIC).build())
+            .with_method(FunctionBuilder::new(c_str!("countEntries"), count_entries).with_flags(ACC_STATIC).build())
+            .with_method(FunctionBuilder::new(c_str!("mismatchedSpec"), mismatched_spec).with_flags(ACC_STATIC).build())
+            .with_method(FunctionBuilder::new(c_str!("appendLocal"), append_local)
+                .with_arg(ArgInfo::new(c_str!("values"), 0, 0, 0))
+                .with_flags(ACC_STATIC)
+                .build())
+            .build()
+    }
+}
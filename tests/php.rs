@@ -0,0 +1,150 @@
+//! Runs the scripts of `tests/php` with the extension of `tests/extension` loaded, and compares
+//! their output with the `.out` file next to them.
+//!
+//! They need the PHP 7.1, 7.2 or 7.3 that solder was built for, so they are ignored by default.
+//! Run them with `cargo test -- --ignored`, which fails if that PHP is missing.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn check_php_version() {
+    let output = Command::new("php")
+        .args(["-n", "-r", "echo PHP_MAJOR_VERSION, '.', PHP_MINOR_VERSION;"])
+        .output()
+        .expect("The PHP tests need php 7.1 to 7.3 in the PATH");
+    let version = String::from_utf8_lossy(&output.stdout);
+    assert!(["7.1", "7.2", "7.3"].contains(&version.as_ref()), "The PHP tests need php 7.1 to 7.3, found {}", version);
+}
+
+fn build_extension() -> PathBuf {
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "-p", "solder-tests"])
+        .status()
+        .expect("Failed to run cargo");
+    assert!(status.success(), "Failed to build the test extension");
+    let target_dir = env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target"));
+    target_dir.join("debug").join(format!("{}solder_tests{}", env::consts::DLL_PREFIX, env::consts::DLL_SUFFIX))
+}
+
+fn run(script: &str) {
+    check_php_version();
+    let extension = build_extension();
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("php");
+    let output = Command::new("php")
+        .arg("-n")
+        .arg(format!("-dextension={}", extension.display()))
+        .arg(directory.join(format!("{}.php", script)))
+        .output()
+        .expect("Failed to run php");
+    let expected = fs::read_to_string(directory.join(format!("{}.out", script))).unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected, "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+#[ignore = "needs PHP 7.1 to 7.3"]
+fn execute_data() {
+    run("execute_data");
+}
+
+#[test]
+#[ignore = "needs PHP 7.1 to 7.3"]
+fn strings() {
+    run("strings");
+}
+
+#[test]
+#[ignore = "needs PHP 7.1 to 7.3"]
+fn conversions() {
+    run("conversions");
+}
+
+#[test]
+#[ignore = "needs PHP 7.1 to 7.3"]
+fn arguments() {
+    run("arguments");
+}
+
+#[test]
+#[ignore = "needs PHP 7.1 to 7.3"]
+fn arrays() {
+    run("arrays");
+}
+
+#[test]
+#[ignore = "needs PHP 7.1 to 7.3"]
+fn exceptions() {
+    run("exceptions");
+}
+
+#[test]
+#[ignore = "needs PHP 7.1 to 7.3"]
+fn module_info() {
+    run("module_info");
+}
+
+#[test]
+#[ignore = "needs PHP 7.1 to 7.3"]
+fn classes() {
+    run("classes");
+}
+
+#[test]
+#[ignore = "needs PHP 7.1 to 7.3"]
+fn ini() {
+    run("ini");
+}
+
+#[test]
+#[ignore = "needs PHP 7.1 to 7.3"]
+fn constants() {
+    run("constants");
+}
+
+#[test]
+#[ignore = "needs PHP 7.1 to 7.3"]
+fn globals() {
+    // Each run is a new request, which starts from fresh globals
+    run("globals");
+    run("globals");
+}
+
+#[test]
+#[ignore = "needs PHP 7.1 to 7.3"]
+fn startup_panic() {
+    check_php_version();
+    let extension = build_extension();
+    let output = Command::new("php")
+        .arg("-n")
+        .arg(format!("-dextension={}", extension.display()))
+        .args(["-r", "echo 'started';"])
+        .env("SOLDER_TESTS_STARTUP_PANIC", "1")
+        .output()
+        .expect("Failed to run php");
+    // PHP exits when a module fails to start, after logging the errors
+    let errors = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(errors.contains("Rust panic: Startup failed"), "{}", errors);
+    assert!(errors.contains("Unable to start solder_tests module"), "{}", errors);
+}
+
+#[test]
+#[ignore = "needs PHP 7.1 to 7.3"]
+fn globals_panic() {
+    check_php_version();
+    let extension = build_extension();
+    let output = Command::new("php")
+        .arg("-n")
+        .arg(format!("-dextension={}", extension.display()))
+        .args(["-r", "try { count_call('first'); } catch (Error $error) { echo $error->getMessage(); }"])
+        .env("SOLDER_TESTS_GLOBALS_PANIC", "1")
+        .output()
+        .expect("Failed to run php");
+    // The globals are created when the module is loaded, before any PHP code can catch an exception
+    let errors = String::from_utf8_lossy(&output.stderr);
+    assert!(errors.contains("Rust panic: Globals failed"), "{}", errors);
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("Rust panic: The module globals could not be created"));
+}
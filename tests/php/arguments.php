@@ -0,0 +1,27 @@
+<?php
+
+$x = 1;
+set_reference($x, 5);
+echo $x, "\n";
+
+$values = ['kept'];
+echo SolderTests\Handlers::appendLocal($values), "\n";
+echo implode(',', $values), "\n";
+
+foreach (['greet', 'set_reference', 'describe_arguments'] as $name) {
+    $function = new ReflectionFunction($name);
+    $parameters = array_map(function ($parameter) {
+        return ($parameter->isPassedByReference() ? '&' : '') . ($parameter->isVariadic() ? '...' : '') . '$' . $parameter->getName();
+    }, $function->getParameters());
+    echo $name, '(', implode(', ', $parameters), ') requires ', $function->getNumberOfRequiredParameters(), "\n";
+}
+
+$method = new ReflectionMethod('SolderTests\Handlers', 'appendLocal');
+echo 'appendLocal requires ', $method->getNumberOfRequiredParameters(), "\n";
+
+echo SolderTests\Handlers::countEntries(['a' => 1, 'b' => 2]), "\n";
+try {
+    SolderTests\Handlers::mismatchedSpec('text');
+} catch (Error $error) {
+    echo get_class($error), ': ', $error->getMessage(), "\n";
+}
@@ -0,0 +1,10 @@
+<?php
+
+$values = ['first' => 1];
+$copy = with_entry($values, 'second');
+echo json_encode($values), "\n";
+echo json_encode($copy), "\n";
+
+$literal = with_entry(['first' => 1], 'third');
+echo json_encode($literal), "\n";
+
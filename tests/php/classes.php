@@ -0,0 +1,19 @@
+<?php
+
+$counter = new SolderTests\Counter();
+var_dump($counter->increment(), $counter->increment());
+
+try {
+    clone $counter;
+} catch (Error $error) {
+    echo get_class($error), ': ', $error->getMessage(), "\n";
+}
+
+$counter = new SolderTests\CloneableCounter();
+$counter->increment();
+$counter->label = 'original';
+$copy = clone $counter;
+var_dump($copy->increment(), $copy->label, $counter->increment());
+
+$counter = new SolderTests\LimitedCounter();
+var_dump(get_parent_class($counter), $counter->increment());
@@ -0,0 +1,11 @@
+<?php
+
+var_dump(SOLDER_TESTS_VERSION, SOLDER_TESTS_LIMIT, SOLDER_TESTS_RATIO, SOLDER_TESTS_ENABLED, SOLDER_TESTS_NOTHING);
+var_dump(\SolderTests\MODE_FAST, \SolderTests\DEFAULT_MODE);
+
+// Names are case sensitive
+var_dump(defined('solder_tests_version'), defined('SolderTests\mode_fast'));
+
+$constants = get_defined_constants(true)['solder_tests'];
+ksort($constants);
+var_dump($constants);
@@ -0,0 +1,29 @@
+<?php
+
+echo join_words(['Hello', 'World']), "\n";
+
+try {
+    join_words(['Hello', "\xff"]);
+} catch (TypeError $error) {
+    echo $error->getMessage(), "\n";
+}
+
+echo list_entries(['b' => 2, 'a' => 1, 10 => 3]), "\n";
+
+try {
+    list_entries(["\xff" => 1]);
+} catch (TypeError $error) {
+    echo $error->getMessage(), "\n";
+}
+
+$words = ['Hello', 'there'];
+$word = &$words[1];
+$word = 'World';
+echo join_words($words), "\n";
+
+$counts = ['a' => 1];
+$count = &$counts['a'];
+set_reference($count, 9);
+echo list_entries($counts), "\n";
+
+echo implode(',', kinds(null, true, 1, 1.5, 'a', [], new stdClass(), STDIN)), "\n";
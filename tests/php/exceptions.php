@@ -0,0 +1,28 @@
+<?php
+
+function report(callable $call) {
+    try {
+        $call();
+        echo "nothing thrown\n";
+    } catch (Throwable $error) {
+        echo get_class($error), ' (', $error->getCode(), '): ', $error->getMessage(), "\n";
+        return $error;
+    }
+}
+
+report(function () { SolderTests\Handlers::checkName('Bruno'); });
+report(function () { SolderTests\Handlers::checkName(''); });
+
+echo parse_number(' 42 '), "\n";
+$error = report(function () { parse_number('forty-two'); });
+// Properties declared by ExceptionBuilder are protected
+$input = new ReflectionProperty($error, 'input');
+$input->setAccessible(true);
+var_dump($error instanceof RuntimeException, $input->isProtected(), $input->getValue($error));
+echo get_class($error->getPrevious()), ': ', $error->getPrevious()->getMessage(), "\n";
+
+$settings = ['size' => '10', 'mode' => 'fast'];
+echo read_setting($settings, 'size'), "\n";
+report(function () use ($settings) { read_setting($settings, 'color'); });
+report(function () use ($settings) { read_setting($settings, 'mode'); });
+report(function () { fail_with_message('Plain failure'); });
@@ -0,0 +1,4 @@
+<?php
+
+echo describe_arguments(), "\n";
+echo describe_arguments('a', 1, 'b', [], null), "\n";
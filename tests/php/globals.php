@@ -0,0 +1,5 @@
+<?php
+
+// The globals keep their values between the calls of a request
+var_dump(count_call('first'), count_call('second'), count_call('third'));
+var_dump(called_names());
@@ -0,0 +1,18 @@
+<?php
+
+var_dump(ini_get('solder_tests.greeting'), ini_get('solder_tests.limit'), ini_get('solder_tests.enabled'));
+var_dump(read_ini('solder_tests.greeting', 'string'), read_ini('solder_tests.limit', 'integer'), read_ini('solder_tests.enabled', 'bool'));
+
+var_dump(ini_set('solder_tests.limit', '2K'), read_ini('solder_tests.limit', 'integer'), read_ini('solder_tests.limit', 'float'));
+var_dump(ini_set('solder_tests.limit', 'lots'), ini_get('solder_tests.limit'));
+var_dump(ini_set('solder_tests.enabled', 'maybe'), ini_set('solder_tests.enabled', 'off'), read_ini('solder_tests.enabled', 'bool'));
+
+var_dump(ini_set('solder_tests.mode', 'slow'), ini_set('solder_tests.mode', 'safe'), ini_get('solder_tests.mode'));
+try {
+    ini_set('solder_tests.mode', 'panic');
+} catch (Error $error) {
+    echo preg_replace('/ at .*/', '', $error->getMessage()), "\n";
+}
+var_dump(ini_get('solder_tests.mode'));
+
+var_dump(ini_get('solder_tests.missing'), read_ini('solder_tests.missing', 'integer'));
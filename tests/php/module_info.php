@@ -0,0 +1,32 @@
+<?php
+
+function module_info() {
+    ob_start();
+    try {
+        (new ReflectionExtension('solder_tests'))->info();
+    } finally {
+        $info = ob_get_clean();
+    }
+    return $info;
+}
+
+// The local value differs from the master one
+ini_set('solder_tests.mode', 'safe');
+
+foreach (explode("\n", module_info()) as $line) {
+    if (preg_match('/^Functions => (.*)/', $line, $match)) {
+        $functions = explode(', ', $match[1]);
+        var_dump(in_array('greet', $functions), in_array('join_words', $functions));
+    } elseif (preg_match('/^PHP API => (\d+)$/', $line, $match)) {
+        var_dump(in_array($match[1], ['20160303', '20170718', '20180731']));
+    } elseif (preg_match('/^(solder_tests|Version|Solder version|Classes|Rust |Directive)/', $line)) {
+        echo $line, "\n";
+    }
+}
+
+putenv('SOLDER_TESTS_INFO_PANIC=1');
+try {
+    module_info();
+} catch (Error $error) {
+    echo preg_replace('/ at .*/', '', $error->getMessage()), "\n";
+}
@@ -0,0 +1,21 @@
+<?php
+
+echo greet('Bruno'), "\n";
+echo greet('Bruno', 'Olá'), "\n";
+echo byte_length("\xff\xfe"), "\n";
+echo SolderTests\Handlers::repeat('ab', 3, '-'), "\n";
+
+foreach ([
+    function () { greet("\xff"); },
+    function () { greet('Bruno', "\xff"); },
+    function () { SolderTests\Handlers::repeat("\xff", 2); },
+] as $call) {
+    try {
+        $call();
+    } catch (TypeError $error) {
+        echo $error->getMessage(), "\n";
+    }
+}
+
+$strings = shared_string("caf\xc3\xa9\0");
+var_dump($strings[0] === "caf\xc3\xa9\0", $strings[0] === $strings[1], bin2hex($strings[2]));
@@ -0,0 +1,7 @@
+use solder::c_str;
+use solder::zend::*;
+
+fn main() {
+    ModuleBuilder::new(c_str!("hello"), c_str!("0.1.0"))
+        .with_constant(c_str!("HELLO_MODES"), vec!["fast", "safe"]);
+}
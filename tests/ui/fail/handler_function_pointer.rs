@@ -0,0 +1,9 @@
+use solder::c_str;
+use solder::zend::*;
+
+fn hello(_data: &ExecuteData, _retval: &mut Zval) {}
+
+fn main() {
+    let handler: fn(&ExecuteData, &mut Zval) = hello;
+    FunctionBuilder::new(c_str!("hello"), handler).build();
+}
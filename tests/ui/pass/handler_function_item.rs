@@ -0,0 +1,14 @@
+use solder::c_str;
+use solder::zend::*;
+
+fn hello(_data: &ExecuteData, _retval: &mut Zval) {}
+
+#[allow(dead_code)]
+fn functions() -> Vec<Function> {
+    vec![
+        FunctionBuilder::new(c_str!("hello"), hello).build(),
+        FunctionBuilder::new(c_str!("closure"), |_data: &ExecuteData, _retval: &mut Zval| {}).build(),
+    ]
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+fn parse(text: &str) -> Result<i64, String> {
+    text.parse().map_err(|_| format!("Not a number: {}", text))
+}
+
+#[allow(dead_code)]
+fn handler(_data: &solder::zend::ExecuteData, retval: &mut solder::zend::Zval) {
+    solder::php_return_result!(retval, parse("42"));
+}
+
+#[allow(dead_code)]
+fn other_handler(_data: &solder::zend::ExecuteData, retval: &mut solder::zend::Zval) {
+    solder::php_return!(retval, "done");
+}
+
+fn main() {}